@@ -0,0 +1,285 @@
+// renaming a bunch of files at once by editing their names in $EDITOR
+use std::{
+    collections::HashSet,
+    env,
+    ffi::{CString, OsString},
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    os::unix::{
+        ffi::{OsStrExt, OsStringExt},
+        fs::OpenOptionsExt,
+    },
+    path::{Path, PathBuf},
+    process,
+};
+
+// waiting for the editor to be done with the file
+pub struct Pending {
+    dir: PathBuf,
+    names: Vec<String>,
+    pub file: PathBuf,
+}
+
+pub struct Step {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+// a plan is the list of steps to run in order, temporary names included, and
+// how many files actually end up with a new name
+pub struct Plan {
+    pub steps: Vec<Step>,
+    pub renamed: usize,
+}
+
+// a directory in /tmp only we can get into, made with a name nobody could
+// guess so nothing can be waiting there already
+fn private_dir() -> io::Result<PathBuf> {
+    let template = env::temp_dir().join("lga-bulkrename-XXXXXX");
+    let mut template = CString::new(template.as_os_str().as_bytes())?.into_bytes_with_nul();
+    let made = unsafe { libc::mkdtemp(template.as_mut_ptr() as *mut libc::c_char) };
+    if made.is_null() {
+        return Err(io::Error::last_os_error());
+    }
+    template.pop();
+    Ok(PathBuf::from(OsString::from_vec(template)))
+}
+
+// writes one name per line to a temp file for the editor
+pub fn start(dir: &Path, paths: &[PathBuf]) -> io::Result<Pending> {
+    let names: Vec<String> = paths
+        .iter()
+        .filter_map(|p| p.file_name())
+        .map(|n| n.to_string_lossy().to_string())
+        .collect();
+    if names.iter().any(|n| n.contains('\n')) {
+        return Err(io::Error::other(
+            "cant bulkrename names with newlines in them",
+        ));
+    }
+    let file = private_dir()?.join("names.txt");
+    let written = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&file)
+        .and_then(|mut f| f.write_all((names.join("\n") + "\n").as_bytes()));
+    if let Err(e) = written {
+        cleanup(&file);
+        return Err(e);
+    }
+    Ok(Pending {
+        dir: dir.to_path_buf(),
+        names,
+        file,
+    })
+}
+
+// reads the edited names back and works out what to do with them
+pub fn finish(pending: Pending) -> Result<Plan, String> {
+    let edited = fs::read_to_string(&pending.file).map_err(|e| e.to_string());
+    cleanup(&pending.file);
+    let edited: Vec<String> = edited?.lines().map(str::to_string).collect();
+    plan(&pending.dir, &pending.names, &edited)
+}
+
+// the file and the directory it is in, with whatever the editor left there
+fn cleanup(file: &Path) {
+    if let Some(dir) = file.parent() {
+        let _ = fs::remove_dir_all(dir);
+    }
+}
+
+pub fn plan(dir: &Path, names: &[String], edited: &[String]) -> Result<Plan, String> {
+    if names.len() != edited.len() {
+        return Err(format!(
+            "expected {} lines, got {}, dont add or remove lines",
+            names.len(),
+            edited.len()
+        ));
+    }
+    if let Some(name) = edited
+        .iter()
+        .find(|n| n.is_empty() || n.contains('/') || *n == "." || *n == "..")
+    {
+        return Err(format!("not a valid name: \"{name}\""));
+    }
+    let mut seen = HashSet::new();
+    if let Some(name) = edited.iter().find(|n| !seen.insert(n.as_str())) {
+        return Err(format!("more than one file would be called {name}"));
+    }
+    let moves: Vec<(&String, &String)> = names
+        .iter()
+        .zip(edited)
+        .filter(|(from, to)| from != to)
+        .collect();
+    let sources: HashSet<&String> = moves.iter().map(|(from, _)| *from).collect();
+    // taken by something that isnt moving out of the way
+    for (_, to) in &moves {
+        if dir.join(to).symlink_metadata().is_ok() && !sources.contains(to) {
+            return Err(format!("{to} already exists"));
+        }
+    }
+    let targets: HashSet<&String> = moves.iter().map(|(_, to)| *to).collect();
+    let mut steps = vec![];
+    let mut current = vec![];
+    // anything sitting where another file wants to go gets parked under a
+    // temporary name first, that takes care of swaps and cycles
+    for (i, (name, to)) in moves.iter().enumerate() {
+        let from = dir.join(name);
+        if targets.contains(name) {
+            let parked = temporary(dir, i);
+            steps.push(Step {
+                from,
+                to: parked.to_path_buf(),
+            });
+            current.push((parked, dir.join(to)));
+        } else {
+            current.push((from, dir.join(to)));
+        }
+    }
+    for (from, to) in current {
+        steps.push(Step { from, to });
+    }
+    Ok(Plan {
+        steps,
+        renamed: moves.len(),
+    })
+}
+
+fn temporary(dir: &Path, i: usize) -> PathBuf {
+    (0..)
+        .map(|n| dir.join(format!(".lga-bulkrename-{}-{i}-{n}", process::id())))
+        .find(|p| p.symlink_metadata().is_err())
+        .unwrap_or_default()
+}
+
+// runs the steps in order and stops at the first one that fails, returns
+// the steps that went through
+pub fn run(steps: Vec<Step>) -> (Vec<Step>, Option<String>) {
+    let mut done = vec![];
+    for step in steps {
+        if step.to.symlink_metadata().is_ok() {
+            let error = format!("{} already exists", step.to.display());
+            return (done, Some(error));
+        }
+        match fs::rename(&step.from, &step.to) {
+            Ok(_) => done.push(step),
+            Err(e) => {
+                let error = format!("{}: {e}", step.from.display());
+                return (done, Some(error));
+            }
+        }
+    }
+    (done, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testdir::TestDir;
+    use std::os::unix::fs::PermissionsExt;
+
+    // an empty directory with these files in it
+    fn dir(test: &str, files: &[&str]) -> TestDir {
+        let dir = TestDir::new(test);
+        for file in files {
+            fs::write(dir.join(file), file).unwrap();
+        }
+        dir
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    // runs the plan and returns what each file has in it afterwards, by name
+    fn apply(dir: &Path, plan: Plan) -> Vec<(String, String)> {
+        let (_, error) = run(plan.steps);
+        assert_eq!(error, None);
+        let mut files: Vec<(String, String)> = fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .map(|p| {
+                let name = p.file_name().unwrap().to_string_lossy().to_string();
+                (name, fs::read_to_string(&p).unwrap())
+            })
+            .collect();
+        files.sort();
+        files
+    }
+
+    fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(a, b)| (a.to_string(), b.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn plain_renames() {
+        let dir = dir("plain", &["a", "b"]);
+        let plan = plan(&dir, &names(&["a", "b"]), &names(&["c", "b"])).unwrap();
+        assert_eq!(plan.renamed, 1);
+        assert_eq!(plan.steps.len(), 1);
+        assert_eq!(apply(&dir, plan), pairs(&[("b", "b"), ("c", "a")]));
+    }
+
+    #[test]
+    fn swaps() {
+        let dir = dir("swap", &["a", "b"]);
+        let plan = plan(&dir, &names(&["a", "b"]), &names(&["b", "a"])).unwrap();
+        assert_eq!(plan.renamed, 2);
+        assert_eq!(apply(&dir, plan), pairs(&[("a", "b"), ("b", "a")]));
+    }
+
+    #[test]
+    fn cycles() {
+        let dir = dir("cycle", &["a", "b", "c"]);
+        let plan = plan(&dir, &names(&["a", "b", "c"]), &names(&["b", "c", "a"])).unwrap();
+        assert_eq!(plan.renamed, 3);
+        assert_eq!(
+            apply(&dir, plan),
+            pairs(&[("a", "c"), ("b", "a"), ("c", "b")])
+        );
+    }
+
+    #[test]
+    fn chains_onto_names_being_freed() {
+        let dir = dir("chain", &["a", "b"]);
+        let plan = plan(&dir, &names(&["a", "b"]), &names(&["b", "c"])).unwrap();
+        assert_eq!(apply(&dir, plan), pairs(&[("b", "a"), ("c", "b")]));
+    }
+
+    #[test]
+    fn collisions() {
+        // with a file that isnt being renamed
+        let dir = dir("collision", &["a", "b", "other"]);
+        let error = plan(&dir, &names(&["a", "b"]), &names(&["other", "b"]));
+        assert_eq!(error.err().unwrap(), "other already exists");
+        // with each other
+        let error = plan(&dir, &names(&["a", "b"]), &names(&["c", "c"]));
+        assert_eq!(error.err().unwrap(), "more than one file would be called c");
+    }
+
+    #[test]
+    fn bad_edits() {
+        let dir = dir("bad", &["a", "b"]);
+        assert!(plan(&dir, &names(&["a", "b"]), &names(&["a"])).is_err());
+        for name in ["", ".", "..", "x/y"] {
+            assert!(plan(&dir, &names(&["a", "b"]), &names(&[name, "b"])).is_err());
+        }
+    }
+
+    #[test]
+    fn editing_in_a_private_file() {
+        let pending = start(Path::new("/x"), &[PathBuf::from("/x/a")]).unwrap();
+        let dir = pending.file.parent().unwrap().to_path_buf();
+        assert_eq!(dir.metadata().unwrap().permissions().mode() & 0o777, 0o700);
+        assert_eq!(fs::read_to_string(&pending.file).unwrap(), "a\n");
+        fs::write(&pending.file, "b\n").unwrap();
+        let plan = finish(pending).unwrap();
+        assert_eq!(plan.steps[0].to, PathBuf::from("/x/b"));
+        assert!(!dir.exists());
+    }
+}
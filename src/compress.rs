@@ -0,0 +1,188 @@
+// making archives out of files and directories, for :compress. the kind of
+// archive comes from the name it gets
+use crate::copy::{Progress, Report};
+use chrono::{DateTime, Datelike, Local, Timelike};
+use flate2::{write::GzEncoder, Compression};
+use std::{
+    fs::{self, File, Metadata},
+    io::{self, Error, Read, Write},
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+};
+use xz2::write::XzEncoder;
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+pub enum Kind {
+    Tar,
+    TarGz,
+    TarXz,
+    Zip,
+}
+
+// the suffixes lga knows how to write, longest first so .tar.gz isnt a .tar
+const SUFFIXES: [(&str, Kind); 6] = [
+    (".tar.gz", Kind::TarGz),
+    (".tar.xz", Kind::TarXz),
+    (".tgz", Kind::TarGz),
+    (".txz", Kind::TarXz),
+    (".tar", Kind::Tar),
+    (".zip", Kind::Zip),
+];
+
+pub fn kind(name: &Path) -> Option<&'static Kind> {
+    let name = name.file_name()?.to_str()?.to_lowercase();
+    SUFFIXES
+        .iter()
+        .find(|(suffix, _)| name.len() > suffix.len() && name.ends_with(suffix))
+        .map(|(_, kind)| kind)
+}
+
+// the name without the archive suffix, where :extract puts things by default
+pub fn stem(archive: &Path) -> PathBuf {
+    let stem = archive.file_stem().unwrap_or_default().to_string_lossy();
+    let stem = match stem.to_lowercase().ends_with(".tar") {
+        true => &stem[..stem.len() - 4],
+        false => &stem,
+    };
+    archive.with_file_name(stem)
+}
+
+// everything that goes in, with the name it gets in the archive. names are
+// relative to the directory each source is in
+fn walk(path: &Path, name: PathBuf, archive: &Path, entries: &mut Vec<(PathBuf, PathBuf)>) {
+    // dont put the archive in itself
+    if path == archive {
+        return;
+    }
+    entries.push((path.to_path_buf(), name.to_path_buf()));
+    if path.symlink_metadata().is_ok_and(|m| m.is_dir()) {
+        let mut children: Vec<PathBuf> = fs::read_dir(path)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .collect();
+        children.sort();
+        for child in children {
+            let child_name = name.join(child.file_name().unwrap_or_default());
+            walk(&child, child_name, archive, entries);
+        }
+    }
+}
+
+// counts the bytes as they are read
+struct Counting<'a> {
+    file: File,
+    report: Report<'a>,
+}
+
+impl Read for Counting<'_> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        let n = self.file.read(buffer)?;
+        (self.report)(Progress::Bytes(n as u64))?;
+        Ok(n)
+    }
+}
+
+fn tar<W: Write>(writer: W, entries: &[(PathBuf, PathBuf)], report: Report) -> io::Result<W> {
+    let mut builder = tar::Builder::new(writer);
+    builder.follow_symlinks(false);
+    for (path, name) in entries {
+        let metadata = path.symlink_metadata()?;
+        if metadata.is_file() {
+            let mut header = tar::Header::new_gnu();
+            header.set_metadata(&metadata);
+            let reader = Counting {
+                file: File::open(path)?,
+                report: &mut *report,
+            };
+            builder.append_data(&mut header, name, reader)?;
+        } else {
+            // directories, symlinks and the odd fifo
+            builder.append_path_with_name(path, name)?;
+        }
+        // directories dont count as files, same as when copying
+        if !metadata.is_dir() {
+            report(Progress::File)?;
+        }
+    }
+    builder.into_inner()
+}
+
+// zip times are local and only go down to two seconds
+fn zip_time(metadata: &Metadata) -> Option<zip::DateTime> {
+    let modified: DateTime<Local> = metadata.modified().ok()?.into();
+    zip::DateTime::from_date_and_time(
+        modified.year().try_into().ok()?,
+        modified.month() as u8,
+        modified.day() as u8,
+        modified.hour() as u8,
+        modified.minute() as u8,
+        modified.second() as u8,
+    )
+    .ok()
+}
+
+fn zip(file: File, entries: &[(PathBuf, PathBuf)], report: Report) -> io::Result<()> {
+    let mut zip = ZipWriter::new(file);
+    for (path, name) in entries {
+        let metadata = path.symlink_metadata()?;
+        let mut options = SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Deflated)
+            .unix_permissions(metadata.permissions().mode() & 0o7777)
+            .large_file(metadata.len() >= u32::MAX as u64);
+        if let Some(time) = zip_time(&metadata) {
+            options = options.last_modified_time(time);
+        }
+        let name = name.to_string_lossy();
+        if metadata.is_dir() {
+            zip.add_directory(name, options).map_err(Error::other)?;
+            continue;
+        } else if metadata.is_symlink() {
+            let link = fs::read_link(path)?;
+            zip.add_symlink(name, link.to_string_lossy(), options)
+                .map_err(Error::other)?;
+        } else if metadata.is_file() {
+            zip.start_file(name, options).map_err(Error::other)?;
+            let mut reader = Counting {
+                file: File::open(path)?,
+                report: &mut *report,
+            };
+            io::copy(&mut reader, &mut zip)?;
+        } else {
+            // zip has nowhere to put fifos and devices
+            continue;
+        }
+        report(Progress::File)?;
+    }
+    zip.finish().map_err(Error::other)?;
+    Ok(())
+}
+
+// writes sources into a new archive, nothing is left behind when it fails
+pub fn compress(sources: &[PathBuf], archive: &Path, report: Report) -> io::Result<()> {
+    let kind = kind(archive).ok_or(Error::other("not a name lga can compress to"))?;
+    let mut entries = vec![];
+    for source in sources {
+        let name = PathBuf::from(source.file_name().unwrap_or_default());
+        walk(source, name, archive, &mut entries);
+    }
+    let file = File::options().write(true).create_new(true).open(archive)?;
+    let written = match kind {
+        Kind::Tar => tar(file, &entries, report).map(drop),
+        Kind::TarGz => tar(
+            GzEncoder::new(file, Compression::default()),
+            &entries,
+            report,
+        )
+        .and_then(|gz| gz.finish().map(drop)),
+        Kind::TarXz => {
+            tar(XzEncoder::new(file, 6), &entries, report).and_then(|xz| xz.finish().map(drop))
+        }
+        Kind::Zip => zip(file, &entries, report),
+    };
+    if written.is_err() {
+        let _ = fs::remove_file(archive);
+    }
+    written
+}
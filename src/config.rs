@@ -0,0 +1,174 @@
+// the config file, ~/.config/lga/lga.toml. it has a version so files written
+// by an older lga can be brought up to date, the first one of those being the
+// tags file everything used to live in
+use crate::{
+    details::DetailsConfig, keymap::KeymapConfig, opener::Openers, paste::PasteConfig,
+    preview::Previews, search::SearchConfig, theme::ThemeConfig, ui::Panes, ListOrder,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::Display,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+use toml::Value;
+
+// bumped whenever something in the file changes shape, migrate knows how to
+// get from each one to the next
+pub const VERSION: i64 = 2;
+
+#[derive(Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub version: i64,
+    // whether dotfiles show up, backspace toggles them and lga remembers
+    pub show_hidden: bool,
+    pub order: ListOrder,
+    // how wide the parent, current and preview columns are next to each other
+    pub columns: [u16; 3],
+    // all of them, no-parent, no-preview or single
+    pub panes: Panes,
+    pub tags: Vec<PathBuf>,
+    pub theme: ThemeConfig,
+    // what the long view shows next to the names
+    pub details: DetailsConfig,
+    // programs to open files with, by kind, media type, extension or glob
+    pub openers: Openers,
+    // what to do when pasting onto names that already exist
+    pub paste: PasteConfig,
+    // what shows up in the right column, by kind, media type, extension or glob
+    pub previews: Previews,
+    pub search: SearchConfig,
+    // keys to actions for each mode, on top of the defaults
+    pub keymap: KeymapConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            version: VERSION,
+            show_hidden: false,
+            order: ListOrder::DirsFirst,
+            columns: [1, 2, 2],
+            panes: Panes::All,
+            tags: vec![],
+            theme: ThemeConfig::default(),
+            details: DetailsConfig::default(),
+            openers: Openers::default(),
+            paste: PasteConfig::default(),
+            previews: Previews::default(),
+            search: SearchConfig::default(),
+            keymap: KeymapConfig::default(),
+        }
+    }
+}
+
+// where the config and the journal live, ~/.config/lga
+pub fn dir() -> Result<PathBuf, String> {
+    let file = confy::get_configuration_file_path("lga", "lga").map_err(|e| e.to_string())?;
+    Ok(file.parent().unwrap_or(Path::new("")).to_path_buf())
+}
+
+fn file(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{name}.toml"))
+}
+
+// brings an older config up to VERSION, true if anything had to change.
+// files from before there was a version are version 0
+fn migrate(value: &mut Value) -> Result<bool, String> {
+    let table = value
+        .as_table_mut()
+        .ok_or("the config has to be a table of settings")?;
+    let version = match table.get("version") {
+        None => 0,
+        Some(Value::Integer(version)) => *version,
+        Some(_) => return Err("version has to be a number".to_string()),
+    };
+    if version > VERSION {
+        return Err(format!(
+            "version {version} is from a newer lga, this one goes up to {VERSION}"
+        ));
+    }
+    // 0 is the old tags file, which already looks like 1 apart from that.
+    // 2 has a [theme] where 1 had [colors], which were only colors
+    if version < 2 {
+        if let Some(Value::Table(colors)) = table.remove("colors") {
+            let mut theme = toml::Table::new();
+            for (name, color) in colors {
+                let color = match color {
+                    Value::String(color) => color,
+                    _ => return Err(format!("colors.{name} has to be a color")),
+                };
+                let look = match name.as_str() {
+                    "parent" | "listing" => format!("bold {color}"),
+                    "cursor" => format!("bold black on {color}"),
+                    _ => color,
+                };
+                theme.insert(name, Value::String(look));
+            }
+            table.insert("theme".to_string(), Value::Table(theme));
+        }
+    }
+    table.insert("version".to_string(), Value::Integer(VERSION));
+    Ok(version != VERSION)
+}
+
+// what serde cant tell on its own
+fn validate(config: &Config) -> Result<(), String> {
+    if config.columns[1] == 0 {
+        return Err("columns: the middle one is where the files are, it cant be 0".to_string());
+    }
+    if config.previews.limit == 0 {
+        return Err("previews.limit: previews need at least a byte to show".to_string());
+    }
+    Ok(())
+}
+
+// the config and a note when it had to be brought up to date, or what is
+// wrong with it
+pub fn load(dir: &Path) -> Result<(Config, Option<String>), String> {
+    let path = file(dir, "lga");
+    let old = file(dir, "tags");
+    let (from, text) = match fs::read_to_string(&path) {
+        Ok(text) => (path.to_path_buf(), text),
+        Err(e) if e.kind() == ErrorKind::NotFound => match fs::read_to_string(&old) {
+            Ok(text) => (old.to_path_buf(), text),
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                let config = Config::default();
+                store(&config, dir)?;
+                return Ok((config, None));
+            }
+            Err(e) => return Err(format!("{}: {e}", old.display())),
+        },
+        Err(e) => return Err(format!("{}: {e}", path.display())),
+    };
+    let fail = |e: &dyn Display| format!("{}: {}", from.display(), e.to_string().trim_end());
+    let mut value: Value = toml::from_str(&text).map_err(|e| fail(&e))?;
+    let migrated = migrate(&mut value).map_err(|e| fail(&e))?;
+    let config: Config = match migrated {
+        // straight from the text, so errors point at the right line
+        false => toml::from_str(&text),
+        true => value.try_into(),
+    }
+    .map_err(|e| fail(&e))?;
+    validate(&config).map_err(|e| fail(&e))?;
+    let note = match (from == path, migrated) {
+        (true, false) => None,
+        (true, true) => Some(format!("brought {} up to date", path.display())),
+        // the old file is left alone, nothing reads it anymore
+        (false, _) => Some(format!(
+            "the config lives in {} now, {} can go",
+            path.display(),
+            old.display()
+        )),
+    };
+    if note.is_some() {
+        store(&config, dir)?;
+    }
+    Ok((config, note))
+}
+
+pub fn store(config: &Config, dir: &Path) -> Result<(), String> {
+    confy::store_path(file(dir, "lga"), config).map_err(|e| e.to_string())
+}
@@ -0,0 +1,437 @@
+// the copy engine. copies trees the way cp -a would: symlinks stay symlinks,
+// special files get recreated, and modes, owners, times and extended
+// attributes come along. file contents go through reflinks or
+// copy_file_range when the filesystem can do it, and holes in sparse files
+// stay holes
+use std::{
+    ffi::CString,
+    fs::{self, File, Metadata, OpenOptions},
+    io::{self, Error, ErrorKind, Read, Seek, SeekFrom, Write},
+    os::unix::{
+        ffi::OsStrExt,
+        fs::{lchown, symlink, MetadataExt, OpenOptionsExt, PermissionsExt},
+        io::AsRawFd,
+    },
+    path::Path,
+};
+
+const CHUNK: usize = 1 << 20;
+
+pub enum Progress {
+    // this many more bytes are done
+    Bytes(u64),
+    // one more file is done
+    File,
+}
+
+// a progress callback can stop the copy by returning an error
+pub type Report<'a> = &'a mut dyn FnMut(Progress) -> io::Result<()>;
+
+fn cstring(path: &Path) -> io::Result<CString> {
+    CString::new(path.as_os_str().as_bytes()).map_err(|_| Error::from(ErrorKind::InvalidInput))
+}
+
+fn is_dir(path: &Path) -> bool {
+    path.symlink_metadata().is_ok_and(|m| m.is_dir())
+}
+
+fn check(result: libc::c_int) -> io::Result<()> {
+    match result {
+        -1 => Err(Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
+
+pub fn copy(src: &Path, dst: &Path, report: Report) -> io::Result<()> {
+    let metadata = src.symlink_metadata()?;
+    let file_type = metadata.file_type();
+    if file_type.is_symlink() {
+        symlink(fs::read_link(src)?, dst)?;
+        report(Progress::File)?;
+    } else if file_type.is_dir() {
+        match fs::create_dir(dst) {
+            // a symlink to a directory doesnt count, that would copy into
+            // wherever it points
+            Err(e) if e.kind() == ErrorKind::AlreadyExists && is_dir(dst) => {}
+            result => result?,
+        }
+        for entry in fs::read_dir(src)? {
+            let entry = entry?;
+            copy(&entry.path(), &dst.join(entry.file_name()), report)?;
+        }
+    } else if file_type.is_file() {
+        copy_file(src, dst, &metadata, report)?;
+        report(Progress::File)?;
+    } else {
+        // fifos, sockets and device nodes, devices need root
+        let path = cstring(dst)?;
+        check(unsafe {
+            libc::mknod(
+                path.as_ptr(),
+                metadata.mode() as libc::mode_t,
+                metadata.rdev() as libc::dev_t,
+            )
+        })?;
+        report(Progress::File)?;
+    }
+    // directories last so copying their contents doesnt bump the times again
+    preserve(src, dst, &metadata)
+}
+
+fn copy_file(src: &Path, dst: &Path, metadata: &Metadata, report: Report) -> io::Result<()> {
+    let mut reader = File::open(src)?;
+    // whatever is at dst already, a symlink included, stays as it is. the
+    // mode is private until preserve sets the real one
+    let mut writer = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(dst)?;
+    let len = metadata.len();
+    let copied = (|| {
+        if reflink(&reader, &writer) {
+            return report(Progress::Bytes(len));
+        }
+        let mut done = 0;
+        for (start, end) in segments(&mut reader, len)? {
+            // the hole before this segment
+            report(Progress::Bytes(start - done))?;
+            copy_range(&mut reader, &mut writer, start, end, report)?;
+            done = end;
+        }
+        // a hole at the end only shows up through the length
+        writer.set_len(len)?;
+        report(Progress::Bytes(len - done))
+    })();
+    if copied.is_err() {
+        // no half copied files left behind
+        let _ = fs::remove_file(dst);
+    }
+    copied
+}
+
+#[cfg(target_os = "linux")]
+fn reflink(reader: &File, writer: &File) -> bool {
+    unsafe { libc::ioctl(writer.as_raw_fd(), libc::FICLONE, reader.as_raw_fd()) == 0 }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn reflink(_: &File, _: &File) -> bool {
+    false
+}
+
+// the parts of the file that have data in them, holes are skipped
+#[cfg(target_os = "linux")]
+fn segments(reader: &mut File, len: u64) -> io::Result<Vec<(u64, u64)>> {
+    let fd = reader.as_raw_fd();
+    let mut segments = vec![];
+    let mut offset = 0;
+    while offset < len {
+        let start = unsafe { libc::lseek(fd, offset as libc::off_t, libc::SEEK_DATA) };
+        if start < 0 {
+            match Error::last_os_error().raw_os_error() {
+                // no more data after offset
+                Some(libc::ENXIO) => break,
+                // the filesystem doesnt know about holes, its all data then
+                _ => return Ok(vec![(0, len)]),
+            }
+        }
+        let end = unsafe { libc::lseek(fd, start, libc::SEEK_HOLE) };
+        if end < 0 {
+            return Ok(vec![(0, len)]);
+        }
+        let end = (end as u64).min(len);
+        segments.push((start as u64, end));
+        offset = end;
+    }
+    Ok(segments)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn segments(_: &mut File, len: u64) -> io::Result<Vec<(u64, u64)>> {
+    Ok(vec![(0, len)])
+}
+
+fn copy_range(
+    reader: &mut File,
+    writer: &mut File,
+    start: u64,
+    end: u64,
+    report: Report,
+) -> io::Result<()> {
+    reader.seek(SeekFrom::Start(start))?;
+    writer.seek(SeekFrom::Start(start))?;
+    let mut offset = start;
+    // copy_file_range keeps the data in the kernel, or on the server for nfs
+    #[cfg(target_os = "linux")]
+    while offset < end {
+        let want = (end - offset).min(CHUNK as u64) as usize;
+        let n = unsafe {
+            libc::copy_file_range(
+                reader.as_raw_fd(),
+                std::ptr::null_mut(),
+                writer.as_raw_fd(),
+                std::ptr::null_mut(),
+                want,
+                0,
+            )
+        };
+        match n {
+            // the file got shorter while copying
+            0 => return Ok(()),
+            n if n > 0 => {
+                offset += n as u64;
+                report(Progress::Bytes(n as u64))?;
+            }
+            // not supported between these two, read and write it is
+            _ => break,
+        }
+    }
+    reader.seek(SeekFrom::Start(offset))?;
+    writer.seek(SeekFrom::Start(offset))?;
+    let mut buffer = vec![0; CHUNK.min((end - offset) as usize)];
+    while offset < end {
+        let want = (end - offset).min(CHUNK as u64) as usize;
+        let n = reader.read(&mut buffer[..want])?;
+        if n == 0 {
+            return Ok(());
+        }
+        writer.write_all(&buffer[..n])?;
+        offset += n as u64;
+        report(Progress::Bytes(n as u64))?;
+    }
+    Ok(())
+}
+
+// owner, mode, xattrs and times, in that order since chown clears setuid
+// bits and everything else touches ctime/mtime
+fn preserve(src: &Path, dst: &Path, metadata: &Metadata) -> io::Result<()> {
+    let symlink = metadata.file_type().is_symlink();
+    // only root can give files away
+    if unsafe { libc::geteuid() } == 0 {
+        lchown(dst, Some(metadata.uid()), Some(metadata.gid()))?;
+    }
+    if !symlink {
+        fs::set_permissions(dst, fs::Permissions::from_mode(metadata.mode() & 0o7777))?;
+        copy_xattrs(src, dst)?;
+    }
+    let times = [
+        libc::timespec {
+            tv_sec: metadata.atime() as libc::time_t,
+            tv_nsec: metadata.atime_nsec() as _,
+        },
+        libc::timespec {
+            tv_sec: metadata.mtime() as libc::time_t,
+            tv_nsec: metadata.mtime_nsec() as _,
+        },
+    ];
+    let path = cstring(dst)?;
+    check(unsafe {
+        libc::utimensat(
+            libc::AT_FDCWD,
+            path.as_ptr(),
+            times.as_ptr(),
+            libc::AT_SYMLINK_NOFOLLOW,
+        )
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn copy_xattrs(src: &Path, dst: &Path) -> io::Result<()> {
+    let (src, dst) = (cstring(src)?, cstring(dst)?);
+    let size = unsafe { libc::llistxattr(src.as_ptr(), std::ptr::null_mut(), 0) };
+    if size <= 0 {
+        // nothing there or not supported
+        return Ok(());
+    }
+    let mut names = vec![0u8; size as usize];
+    let size = unsafe { libc::llistxattr(src.as_ptr(), names.as_mut_ptr().cast(), names.len()) };
+    if size < 0 {
+        return Ok(());
+    }
+    for name in names[..size as usize]
+        .split(|&b| b == 0)
+        .filter(|n| !n.is_empty())
+    {
+        let name = CString::new(name).map_err(|_| Error::from(ErrorKind::InvalidData))?;
+        let len = unsafe { libc::lgetxattr(src.as_ptr(), name.as_ptr(), std::ptr::null_mut(), 0) };
+        if len < 0 {
+            continue;
+        }
+        let mut value = vec![0u8; len as usize];
+        let len = unsafe {
+            libc::lgetxattr(
+                src.as_ptr(),
+                name.as_ptr(),
+                value.as_mut_ptr().cast(),
+                value.len(),
+            )
+        };
+        if len < 0 {
+            continue;
+        }
+        let set = unsafe {
+            libc::lsetxattr(
+                dst.as_ptr(),
+                name.as_ptr(),
+                value.as_ptr().cast(),
+                len as usize,
+                0,
+            )
+        };
+        if set < 0 {
+            match Error::last_os_error().raw_os_error() {
+                // the destination cant hold them, or its a namespace like
+                // trusted.* that needs privileges, not worth failing the copy
+                Some(libc::ENOTSUP) | Some(libc::EPERM) => {}
+                _ => return Err(Error::last_os_error()),
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn copy_xattrs(_: &Path, _: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+// how many bytes and files are in there, the way copy sees them
+pub fn measure(path: &Path) -> (u64, usize) {
+    match path.symlink_metadata() {
+        Ok(metadata) if metadata.is_dir() => fs::read_dir(path)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| measure(&entry.path()))
+            .fold((0, 0), |(b, f), (bytes, files)| (b + bytes, f + files)),
+        Ok(metadata) if metadata.is_file() => (metadata.len(), 1),
+        // symlinks and special files are copied as they are, no bytes in them
+        Ok(_) => (0, 1),
+        Err(_) => (0, 0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testdir::TestDir;
+
+    fn copied(src: &Path, dst: &Path) -> io::Result<()> {
+        copy(src, dst, &mut |_| Ok(()))
+    }
+
+    #[test]
+    fn symlinks_stay_symlinks() {
+        let dir = TestDir::new("copy-symlink");
+        fs::write(dir.join("file"), "file").unwrap();
+        symlink("file", dir.join("link")).unwrap();
+        symlink("nowhere", dir.join("dangling")).unwrap();
+        copied(&dir.join("link"), &dir.join("link2")).unwrap();
+        copied(&dir.join("dangling"), &dir.join("dangling2")).unwrap();
+        assert!(dir.join("link2").symlink_metadata().unwrap().is_symlink());
+        assert_eq!(fs::read_link(dir.join("link2")).unwrap(), Path::new("file"));
+        assert_eq!(
+            fs::read_link(dir.join("dangling2")).unwrap(),
+            Path::new("nowhere")
+        );
+    }
+
+    #[test]
+    fn symlinks_at_the_destination_arent_followed() {
+        let dir = TestDir::new("copy-nofollow");
+        fs::write(dir.join("src"), "src").unwrap();
+        fs::write(dir.join("target"), "target").unwrap();
+        symlink(dir.join("target"), dir.join("dst")).unwrap();
+        let error = copied(&dir.join("src"), &dir.join("dst")).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::AlreadyExists);
+        assert_eq!(fs::read_to_string(dir.join("target")).unwrap(), "target");
+        // same for directories, nothing gets copied into where it points
+        fs::create_dir_all(dir.join("tree/sub")).unwrap();
+        fs::write(dir.join("tree/sub/file"), "file").unwrap();
+        fs::create_dir_all(dir.join("out/tree")).unwrap();
+        fs::create_dir(dir.join("elsewhere")).unwrap();
+        symlink(dir.join("elsewhere"), dir.join("out/tree/sub")).unwrap();
+        assert!(copied(&dir.join("tree"), &dir.join("out/tree")).is_err());
+        assert!(!dir.join("elsewhere/file").exists());
+    }
+
+    #[test]
+    fn modes_and_times_come_along() {
+        let dir = TestDir::new("copy-metadata");
+        let src = dir.join("src");
+        fs::create_dir(&src).unwrap();
+        fs::write(src.join("file"), "file").unwrap();
+        fs::set_permissions(src.join("file"), fs::Permissions::from_mode(0o751)).unwrap();
+        fs::set_permissions(&src, fs::Permissions::from_mode(0o710)).unwrap();
+        let old = std::time::UNIX_EPOCH + std::time::Duration::new(1_000_000_000, 123_000);
+        for path in [src.join("file"), src.to_path_buf()] {
+            File::open(&path).unwrap().set_modified(old).unwrap();
+        }
+        let dst = dir.join("dst");
+        copied(&src, &dst).unwrap();
+        for (path, mode) in [(dst.join("file"), 0o751), (dst.to_path_buf(), 0o710)] {
+            let metadata = path.metadata().unwrap();
+            assert_eq!(metadata.mode() & 0o7777, mode);
+            assert_eq!(metadata.modified().unwrap(), old);
+        }
+        // so the test directory can be cleaned up
+        fs::set_permissions(&dst, fs::Permissions::from_mode(0o755)).unwrap();
+        fs::set_permissions(&src, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[test]
+    fn holes_stay_holes() {
+        let dir = TestDir::new("copy-sparse");
+        let src = dir.join("sparse");
+        let len = 64 << 20;
+        let mut file = File::create(&src).unwrap();
+        file.seek(SeekFrom::Start(len / 2)).unwrap();
+        file.write_all(b"data").unwrap();
+        file.set_len(len).unwrap();
+        drop(file);
+        let allocated = |path: &Path| path.metadata().unwrap().blocks() * 512;
+        if allocated(&src) >= len {
+            // the filesystem doesnt do holes, nothing to keep
+            return;
+        }
+        copied(&src, &dir.join("copy")).unwrap();
+        let copy = dir.join("copy");
+        assert_eq!(copy.metadata().unwrap().len(), len);
+        assert!(allocated(&copy) < len);
+        let mut data = vec![0; 4];
+        let mut file = File::open(&copy).unwrap();
+        file.seek(SeekFrom::Start(len / 2)).unwrap();
+        file.read_exact(&mut data).unwrap();
+        assert_eq!(data, b"data");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn xattrs_come_along() {
+        let dir = TestDir::new("copy-xattr");
+        let src = dir.join("src");
+        fs::write(&src, "src").unwrap();
+        let (path, name) = (cstring(&src).unwrap(), CString::new("user.lga").unwrap());
+        let set = unsafe {
+            libc::lsetxattr(path.as_ptr(), name.as_ptr(), b"value".as_ptr().cast(), 5, 0)
+        };
+        if set < 0 {
+            // the filesystem under the temp dir cant hold them
+            return;
+        }
+        copied(&src, &dir.join("dst")).unwrap();
+        let path = cstring(&dir.join("dst")).unwrap();
+        let mut value = vec![0u8; 16];
+        let len = unsafe {
+            libc::lgetxattr(
+                path.as_ptr(),
+                name.as_ptr(),
+                value.as_mut_ptr().cast(),
+                value.len(),
+            )
+        };
+        assert_eq!(len, 5);
+        assert_eq!(&value[..5], b"value");
+    }
+}
@@ -0,0 +1,200 @@
+// the long view, the middle column with size, date, permissions, owner and
+// where links point next to the names, lined up like ls -l
+use crate::vfs;
+use chrono::{DateTime, Local};
+use humansize::{format_size, DECIMAL};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    ffi::CStr,
+    fs::{self, Metadata},
+    mem,
+    os::unix::fs::{MetadataExt, PermissionsExt},
+    path::Path,
+    ptr,
+};
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Detail {
+    Size,
+    Modified,
+    Permissions,
+    Owner,
+    Target,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DetailsConfig {
+    // whether lga starts in the long view
+    pub show: bool,
+    // in the order they show up, the ones at the end go first when it gets
+    // too narrow
+    pub columns: Vec<Detail>,
+}
+
+impl Default for DetailsConfig {
+    fn default() -> Self {
+        DetailsConfig {
+            show: false,
+            columns: vec![
+                Detail::Size,
+                Detail::Modified,
+                Detail::Permissions,
+                Detail::Owner,
+                Detail::Target,
+            ],
+        }
+    }
+}
+
+// names need at least this much room before details get left out
+const NAME: usize = 16;
+
+// user and group names by id, looking them up every frame adds up
+#[derive(Default)]
+pub struct Names {
+    users: HashMap<u32, String>,
+    groups: HashMap<u32, String>,
+}
+
+impl Names {
+    fn user(&mut self, uid: u32) -> &str {
+        self.users.entry(uid).or_insert_with(|| {
+            let mut buf = vec![0; 4096];
+            let mut passwd: libc::passwd = unsafe { mem::zeroed() };
+            let mut found = ptr::null_mut();
+            let result = unsafe {
+                libc::getpwuid_r(uid, &mut passwd, buf.as_mut_ptr(), buf.len(), &mut found)
+            };
+            match result == 0 && !found.is_null() {
+                true => unsafe { CStr::from_ptr(passwd.pw_name) }
+                    .to_string_lossy()
+                    .into_owned(),
+                false => uid.to_string(),
+            }
+        })
+    }
+
+    fn group(&mut self, gid: u32) -> &str {
+        self.groups.entry(gid).or_insert_with(|| {
+            let mut buf = vec![0; 4096];
+            let mut group: libc::group = unsafe { mem::zeroed() };
+            let mut found = ptr::null_mut();
+            let result = unsafe {
+                libc::getgrgid_r(gid, &mut group, buf.as_mut_ptr(), buf.len(), &mut found)
+            };
+            match result == 0 && !found.is_null() {
+                true => unsafe { CStr::from_ptr(group.gr_name) }
+                    .to_string_lossy()
+                    .into_owned(),
+                false => gid.to_string(),
+            }
+        })
+    }
+
+    // what goes next to the name, one string for each of the columns, from
+    // the metadata the listing took. things inside archives dont have any and
+    // only get a size and a date
+    pub fn cells(
+        &mut self,
+        path: &Path,
+        metadata: Option<&Metadata>,
+        columns: &[Detail],
+    ) -> Vec<String> {
+        columns
+            .iter()
+            .map(|detail| match (detail, metadata) {
+                (Detail::Size, Some(metadata)) if metadata.is_dir() => String::new(),
+                (Detail::Size, None) if vfs::is_dir(path) => String::new(),
+                (Detail::Size, Some(metadata)) => format_size(metadata.len(), DECIMAL),
+                (Detail::Size, None) => vfs::len(path)
+                    .map(|len| format_size(len, DECIMAL))
+                    .unwrap_or_default(),
+                (Detail::Modified, Some(metadata)) => date(metadata.modified().ok()),
+                (Detail::Modified, None) => date(vfs::modified(path)),
+                (Detail::Permissions, Some(metadata)) => permissions(metadata),
+                (Detail::Owner, Some(metadata)) => {
+                    let user = self.user(metadata.uid()).to_string();
+                    format!("{user} {}", self.group(metadata.gid()))
+                }
+                (Detail::Target, Some(metadata)) if metadata.is_symlink() => {
+                    match fs::read_link(path) {
+                        Ok(target) => format!("-> {}", target.display()),
+                        Err(_) => "-> ?".to_string(),
+                    }
+                }
+                _ => String::new(),
+            })
+            .collect()
+    }
+}
+
+fn date(time: Option<std::time::SystemTime>) -> String {
+    match time {
+        Some(time) => DateTime::<Local>::from(time)
+            .format("%Y-%m-%d %H:%M")
+            .to_string(),
+        None => String::new(),
+    }
+}
+
+// rwxr-xr-x, with s and t where setuid, setgid and sticky are
+fn permissions(metadata: &Metadata) -> String {
+    let mode = metadata.permissions().mode();
+    let special = [0o4000, 0o2000, 0o1000];
+    (0..3)
+        .flat_map(|i| {
+            let bits = mode >> (6 - i * 3);
+            let execute = match (bits & 1 != 0, mode & special[i] != 0, i) {
+                (true, true, 2) => 't',
+                (false, true, 2) => 'T',
+                (true, true, _) => 's',
+                (false, true, _) => 'S',
+                (true, false, _) => 'x',
+                (false, false, _) => '-',
+            };
+            [
+                if bits & 4 != 0 { 'r' } else { '-' },
+                if bits & 2 != 0 { 'w' } else { '-' },
+                execute,
+            ]
+        })
+        .collect()
+}
+
+// how wide each column is and how many of them fit next to names of at
+// least NAME in width. the ones that dont fit are dropped from the end
+pub fn fit(rows: &[Vec<String>], width: usize) -> Vec<usize> {
+    let count = rows.first().map_or(0, |row| row.len());
+    let mut widths: Vec<usize> = (0..count)
+        .map(|i| {
+            rows.iter()
+                .map(|row| row[i].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    // a space in front of each column, empty ones dont take any room
+    while widths.iter().map(|&w| w + (w > 0) as usize).sum::<usize>() + NAME > width {
+        if widths.pop().is_none() {
+            break;
+        }
+    }
+    widths
+}
+
+// the name cut or padded to width, so the columns after it line up
+pub fn pad(name: &str, width: usize) -> String {
+    let length = name.chars().count();
+    match length > width {
+        true if width > 0 => {
+            let mut cut: String = name.chars().take(width - 1).collect();
+            cut.push('…');
+            cut
+        }
+        true => String::new(),
+        false => format!("{name}{}", " ".repeat(width - length)),
+    }
+}
@@ -0,0 +1,199 @@
+// fuzzy matching for f and :find. the letters of the pattern have to show up
+// in order, matches right after a separator or at a camelCase hump and runs
+// of consecutive letters score better, gaps cost a bit. all lowercase
+// patterns ignore case
+
+const MATCH: i64 = 16;
+// the very first letter
+const START: i64 = 10;
+// after a space, _, -, . or the like
+const BOUNDARY: i64 = 8;
+const CAMEL: i64 = 7;
+const CONSECUTIVE: i64 = 5;
+// skipping letters costs GAP for starting the gap and GAP_EXTEND for each
+// letter skipped
+const GAP: i64 = 3;
+const GAP_EXTEND: i64 = 1;
+
+pub struct Match {
+    // where the candidate was in the list
+    pub index: usize,
+    pub score: i64,
+    // the chars of the candidate that matched, counting chars not bytes
+    pub positions: Vec<usize>,
+}
+
+fn bonus(chars: &[char], j: usize) -> i64 {
+    let current = chars[j];
+    match j.checked_sub(1).map(|i| chars[i]) {
+        None => START,
+        Some(previous) if !previous.is_alphanumeric() && current.is_alphanumeric() => BOUNDARY,
+        Some(previous) if previous.is_lowercase() && current.is_uppercase() => CAMEL,
+        Some(_) => 0,
+    }
+}
+
+// the best way pattern fits in candidate, None if it doesnt
+pub fn score(pattern: &str, candidate: &str) -> Option<(i64, Vec<usize>)> {
+    let ignore_case = !pattern.chars().any(char::is_uppercase);
+    let fold = |c: char| match ignore_case {
+        true => c.to_lowercase().next().unwrap_or(c),
+        false => c,
+    };
+    let pattern: Vec<char> = pattern.chars().map(fold).collect();
+    let chars: Vec<char> = candidate.chars().collect();
+    if pattern.is_empty() {
+        return Some((0, vec![]));
+    }
+    let (m, n) = (pattern.len(), chars.len());
+    if m > n {
+        return None;
+    }
+    let folded: Vec<char> = chars.iter().map(|&c| fold(c)).collect();
+    let bonuses: Vec<i64> = (0..n).map(|j| bonus(&chars, j)).collect();
+    // scores[i][j] is the best score for the first i+1 pattern letters with
+    // the last one at j, from[i][j] is where the one before it went
+    let mut scores = vec![vec![None; n]; m];
+    let mut from = vec![vec![0; n]; m];
+    for j in 0..n {
+        if folded[j] == pattern[0] {
+            scores[0][j] = Some(MATCH + bonuses[j]);
+        }
+    }
+    for i in 1..m {
+        // the best previous letter at least two back, gap paid for up to j
+        let mut gapped: Option<(i64, usize)> = None;
+        for j in i..n {
+            if j >= 2 {
+                let extended = gapped.map(|(score, k)| (score - GAP_EXTEND, k));
+                let opened = scores[i - 1][j - 2].map(|score| (score - GAP - GAP_EXTEND, j - 2));
+                gapped = match (extended, opened) {
+                    (Some(a), Some(b)) => Some(if b.0 >= a.0 { b } else { a }),
+                    (a, b) => a.or(b),
+                };
+            }
+            if folded[j] != pattern[i] {
+                continue;
+            }
+            let consecutive = scores[i - 1][j - 1].map(|score| (score + CONSECUTIVE, j - 1));
+            let best = match (consecutive, gapped) {
+                (Some(a), Some(b)) => Some(if a.0 >= b.0 { a } else { b }),
+                (a, b) => a.or(b),
+            };
+            if let Some((score, k)) = best {
+                scores[i][j] = Some(score + MATCH + bonuses[j]);
+                from[i][j] = k;
+            }
+        }
+    }
+    // the leftmost of the best endings
+    let (mut j, best) = scores[m - 1]
+        .iter()
+        .enumerate()
+        .filter_map(|(j, score)| score.map(|score| (j, score)))
+        .fold(None, |best: Option<(usize, i64)>, (j, score)| match best {
+            Some((_, top)) if top >= score => best,
+            _ => Some((j, score)),
+        })?;
+    let mut positions = vec![0; m];
+    for i in (0..m).rev() {
+        positions[i] = j;
+        j = from[i][j];
+    }
+    Some((best, positions))
+}
+
+// the candidates that match, best first. ties go to the shorter one, then to
+// whichever came first
+pub fn rank<'a>(pattern: &str, candidates: impl Iterator<Item = &'a str>) -> Vec<Match> {
+    let mut matches: Vec<(Match, usize)> = candidates
+        .enumerate()
+        .filter_map(|(index, candidate)| {
+            let (score, positions) = score(pattern, candidate)?;
+            let len = candidate.chars().count();
+            Some((
+                Match {
+                    index,
+                    score,
+                    positions,
+                },
+                len,
+            ))
+        })
+        .collect();
+    matches.sort_by(|(a, a_len), (b, b_len)| {
+        b.score
+            .cmp(&a.score)
+            .then(a_len.cmp(b_len))
+            .then(a.index.cmp(&b.index))
+    });
+    matches.into_iter().map(|(m, _)| m).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn best<'a>(pattern: &str, candidates: &[&'a str]) -> Option<&'a str> {
+        rank(pattern, candidates.iter().copied())
+            .first()
+            .map(|m| candidates[m.index])
+    }
+
+    #[test]
+    fn empty_pattern_matches_everything() {
+        assert_eq!(score("", "anything"), Some((0, vec![])));
+        assert_eq!(rank("", ["a", "b"].into_iter()).len(), 2);
+    }
+
+    #[test]
+    fn letters_have_to_be_in_order() {
+        assert!(score("abc", "a_b_c").is_some());
+        assert!(score("ba", "abc").is_none());
+        assert!(score("abcd", "abc").is_none());
+        assert!(score("x", "abc").is_none());
+    }
+
+    #[test]
+    fn smart_case() {
+        assert!(score("foo", "FooBar").is_some());
+        assert!(score("Foo", "FooBar").is_some());
+        assert!(score("Foo", "foobar").is_none());
+        assert!(score("fB", "foobar").is_none());
+    }
+
+    #[test]
+    fn positions_are_chars() {
+        assert_eq!(score("mrs", "main.rs").unwrap().1, vec![0, 5, 6]);
+        assert_eq!(score("fb", "FooBar").unwrap().1, vec![0, 3]);
+        assert_eq!(score("éb", "café_bar").unwrap().1, vec![3, 5]);
+    }
+
+    #[test]
+    fn consecutive_letters_win() {
+        assert_eq!(best("abc", &["xaxbxcx", "xabcx"]), Some("xabcx"));
+        assert_eq!(best("read", &["rxexaxd", "readme.md"]), Some("readme.md"));
+    }
+
+    #[test]
+    fn word_boundaries_win() {
+        assert_eq!(best("fb", &["afbx", "foo_bar"]), Some("foo_bar"));
+        assert_eq!(best("fb", &["xfxbx", "fooBar"]), Some("fooBar"));
+        // the b after the underscore, not the one in the middle of a word
+        assert_eq!(score("fb", "fabric_bin").unwrap().1, vec![0, 7]);
+    }
+
+    #[test]
+    fn gaps_cost() {
+        let close = score("ab", "axb").unwrap().0;
+        let far = score("ab", "axxxxb").unwrap().0;
+        assert!(close > far);
+    }
+
+    #[test]
+    fn ties_go_to_shorter_then_first() {
+        assert_eq!(best("doc", &["documents", "doc"]), Some("doc"));
+        let ranked = rank("x", ["xa", "xb"].into_iter());
+        assert_eq!(ranked[0].index, 0);
+    }
+}
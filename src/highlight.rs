@@ -0,0 +1,100 @@
+// syntax highlighting for text previews, done once on the preview worker and
+// kept as styled lines
+use std::path::Path;
+use syntect::{
+    easy::HighlightLines,
+    highlighting::{FontStyle, Style as Highlight, Theme, ThemeSet},
+    parsing::{SyntaxReference, SyntaxSet},
+    util::LinesWithEndings,
+};
+use tui::{
+    style::{Color, Modifier, Style},
+    text::{Span, Spans, Text},
+};
+
+pub const DEFAULT_THEME: &str = "base16-ocean.dark";
+// text previews stay as they are
+pub const NO_THEME: &str = "none";
+
+pub struct Highlighter {
+    syntaxes: SyntaxSet,
+    theme: Option<Theme>,
+}
+
+impl Highlighter {
+    // theme is one of the themes that come with syntect or the path to a
+    // .tmTheme file, none for no highlighting, anything else gets the default
+    pub fn new(theme: &str) -> Highlighter {
+        let mut themes = ThemeSet::load_defaults();
+        let theme = match themes.themes.remove(theme) {
+            _ if theme == NO_THEME => None,
+            Some(theme) => Some(theme),
+            None => Some(
+                ThemeSet::get_theme(theme)
+                    .unwrap_or_else(|_| themes.themes.remove(DEFAULT_THEME).unwrap_or_default()),
+            ),
+        };
+        Highlighter {
+            syntaxes: SyntaxSet::load_defaults_newlines(),
+            theme,
+        }
+    }
+
+    // by extension, then the whole name for the likes of Makefile, then the
+    // shebang or modeline
+    fn syntax(&self, path: &Path, text: &str) -> Option<&SyntaxReference> {
+        let extension = path.extension().and_then(|e| e.to_str());
+        let name = path.file_name().and_then(|n| n.to_str());
+        extension
+            .and_then(|e| self.syntaxes.find_syntax_by_extension(e))
+            .or_else(|| name.and_then(|n| self.syntaxes.find_syntax_by_extension(n)))
+            .or_else(|| {
+                let first = text.lines().next()?;
+                self.syntaxes.find_syntax_by_first_line(first)
+            })
+    }
+
+    pub fn highlight(&self, path: &Path, text: String) -> Text<'static> {
+        let (syntax, theme) = match (self.syntax(path, &text), &self.theme) {
+            (Some(syntax), Some(theme)) => (syntax, theme),
+            _ => return Text::raw(text),
+        };
+        let mut lines = HighlightLines::new(syntax, theme);
+        let spans: Vec<Spans> = LinesWithEndings::from(&text)
+            .map(|line| match lines.highlight_line(line, &self.syntaxes) {
+                Ok(regions) => Spans::from(
+                    regions
+                        .into_iter()
+                        .map(|(style, s)| {
+                            Span::styled(
+                                s.trim_end_matches(['\n', '\r']).to_string(),
+                                convert(style),
+                            )
+                        })
+                        .collect::<Vec<Span>>(),
+                ),
+                Err(_) => Spans::from(line.trim_end_matches(['\n', '\r']).to_string()),
+            })
+            .collect();
+        Text::from(spans)
+    }
+}
+
+// the background stays whatever the terminal has
+fn convert(style: Highlight) -> Style {
+    let mut converted = Style::default().fg(Color::Rgb(
+        style.foreground.r,
+        style.foreground.g,
+        style.foreground.b,
+    ));
+    for (font, modifier) in [
+        (FontStyle::BOLD, Modifier::BOLD),
+        (FontStyle::ITALIC, Modifier::ITALIC),
+        (FontStyle::UNDERLINE, Modifier::UNDERLINED),
+    ] {
+        if style.font_style.contains(font) {
+            converted = converted.add_modifier(modifier);
+        }
+    }
+    converted
+}
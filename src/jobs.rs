@@ -0,0 +1,540 @@
+// file operations that take a while run here, on a worker thread, so the ui
+// keeps going while a few gigs get pasted
+use crate::{
+    compress,
+    copy::{self, measure, Progress},
+    journal::Op,
+    vfs::{self, Skipped},
+    PasteMode,
+};
+use humansize::{format_size, DECIMAL};
+use std::{
+    fs,
+    io::{self, Error, ErrorKind},
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum State {
+    Queued,
+    Running,
+    Paused,
+}
+
+pub struct Status {
+    pub state: State,
+    pub bytes: u64,
+    pub total_bytes: u64,
+    pub files: usize,
+    pub total_files: usize,
+    cancel: bool,
+    started: Option<Instant>,
+    paused_at: Option<Instant>,
+    paused_for: Duration,
+}
+
+impl Status {
+    fn new() -> Status {
+        Status {
+            state: State::Queued,
+            bytes: 0,
+            total_bytes: 0,
+            files: 0,
+            total_files: 0,
+            cancel: false,
+            started: None,
+            paused_at: None,
+            paused_for: Duration::ZERO,
+        }
+    }
+
+    // time spent actually working, pauses dont count
+    fn elapsed(&self) -> Duration {
+        let started = match self.started {
+            Some(started) => started,
+            None => return Duration::ZERO,
+        };
+        let paused = match self.paused_at {
+            Some(paused_at) => self.paused_for + paused_at.elapsed(),
+            None => self.paused_for,
+        };
+        started.elapsed().saturating_sub(paused)
+    }
+
+    // bytes per second
+    fn rate(&self) -> u64 {
+        match self.elapsed().as_millis() {
+            0 => 0,
+            ms => (self.bytes as u128 * 1000 / ms) as u64,
+        }
+    }
+
+    fn eta(&self) -> Option<Duration> {
+        match self.rate() {
+            0 => None,
+            rate => Some(Duration::from_secs(
+                self.total_bytes.saturating_sub(self.bytes) / rate,
+            )),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self.state {
+            State::Queued => "queued".to_string(),
+            state => {
+                let percent = match self.total_bytes {
+                    0 => 100,
+                    total => self.bytes * 100 / total,
+                };
+                let eta = match (state, self.eta()) {
+                    (State::Paused, _) => "paused".to_string(),
+                    (_, Some(eta)) => format!("eta {}s", eta.as_secs()),
+                    (_, None) => "eta ?".to_string(),
+                };
+                format!(
+                    "{percent}% {}/{} {}/{} files {}/s {eta}",
+                    format_size(self.bytes, DECIMAL),
+                    format_size(self.total_bytes, DECIMAL),
+                    self.files,
+                    self.total_files,
+                    format_size(self.rate(), DECIMAL),
+                )
+            }
+        }
+    }
+}
+
+pub enum Task {
+    // sources and where each of them goes
+    Paste {
+        mode: PasteMode,
+        items: Vec<(PathBuf, PathBuf)>,
+        // what went to the trash to make room, before the job started
+        trashed: Vec<Op>,
+    },
+    // archives and the directory each of them goes in
+    Extract {
+        items: Vec<(PathBuf, PathBuf)>,
+    },
+    Compress {
+        sources: Vec<PathBuf>,
+        archive: PathBuf,
+    },
+}
+
+pub struct Job {
+    pub id: usize,
+    pub label: String,
+    pub status: Arc<Mutex<Status>>,
+}
+
+// what a job did once its over
+pub struct Finished {
+    pub id: usize,
+    pub label: String,
+    pub ops: Vec<Op>,
+    // trashed to make room for it, the journal keeps it in the same entry
+    pub trashed: Vec<Op>,
+    pub errors: Vec<String>,
+    pub canceled: bool,
+    // the last thing that got created, to select it
+    pub last: Option<PathBuf>,
+}
+
+struct Work {
+    id: usize,
+    label: String,
+    task: Task,
+    status: Arc<Mutex<Status>>,
+}
+
+pub struct Jobs {
+    // queued and running jobs, finished ones get dropped
+    pub list: Vec<Job>,
+    sender: Sender<Work>,
+    finished: Receiver<Finished>,
+    next: usize,
+}
+
+impl Jobs {
+    pub fn new() -> Jobs {
+        let (sender, work) = mpsc::channel::<Work>();
+        let (report, finished) = mpsc::channel();
+        // one worker, jobs run one after the other in the order they came in
+        thread::spawn(move || {
+            for work in work {
+                if report.send(run(work)).is_err() {
+                    break;
+                }
+            }
+        });
+        Jobs {
+            list: vec![],
+            sender,
+            finished,
+            next: 0,
+        }
+    }
+
+    pub fn submit(&mut self, label: String, task: Task) {
+        let status = Arc::new(Mutex::new(Status::new()));
+        self.next += 1;
+        self.list.push(Job {
+            id: self.next,
+            label: label.to_string(),
+            status: Arc::clone(&status),
+        });
+        let _ = self.sender.send(Work {
+            id: self.next,
+            label,
+            task,
+            status,
+        });
+    }
+
+    // collects the jobs that are over
+    pub fn poll(&mut self) -> Vec<Finished> {
+        let finished: Vec<Finished> = self.finished.try_iter().collect();
+        self.list
+            .retain(|job| !finished.iter().any(|f| f.id == job.id));
+        finished
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    pub fn toggle_pause(&self, index: usize) {
+        if let Some(job) = self.list.get(index) {
+            let mut status = job.status.lock().unwrap();
+            match status.state {
+                State::Running => {
+                    status.state = State::Paused;
+                    status.paused_at = Some(Instant::now());
+                }
+                State::Paused => {
+                    status.state = State::Running;
+                    if let Some(paused_at) = status.paused_at.take() {
+                        status.paused_for += paused_at.elapsed();
+                    }
+                }
+                State::Queued => {}
+            }
+        }
+    }
+
+    pub fn cancel(&self, index: usize) {
+        if let Some(job) = self.list.get(index) {
+            job.status.lock().unwrap().cancel = true;
+        }
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        self.list
+            .iter()
+            .map(|job| format!("{}  {}", job.label, job.status.lock().unwrap().describe()))
+            .collect()
+    }
+
+    // the running job, for the footer
+    pub fn summary(&self) -> String {
+        match self.list.first() {
+            Some(job) => {
+                let more = match self.list.len() {
+                    1 => String::new(),
+                    n => format!(" (+{} queued)", n - 1),
+                };
+                format!(
+                    "{}: {}{more}",
+                    job.label,
+                    job.status.lock().unwrap().describe()
+                )
+            }
+            None => String::new(),
+        }
+    }
+}
+
+// blocks while the job is paused, errors out once its canceled
+fn checkpoint(status: &Mutex<Status>) -> io::Result<()> {
+    loop {
+        {
+            let status = status.lock().unwrap();
+            if status.cancel {
+                return Err(Error::new(ErrorKind::Interrupted, "canceled"));
+            }
+            if status.state != State::Paused {
+                return Ok(());
+            }
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+}
+
+// keeps the status up to date, and is where pausing and canceling happen
+fn reporter(status: &Mutex<Status>) -> impl FnMut(Progress) -> io::Result<()> + '_ {
+    |progress| {
+        checkpoint(status)?;
+        let mut status = status.lock().unwrap();
+        match progress {
+            Progress::Bytes(n) => status.bytes += n,
+            Progress::File => status.files += 1,
+        }
+        Ok(())
+    }
+}
+
+fn start(status: &Mutex<Status>, sizes: impl Iterator<Item = (u64, usize)>) {
+    let mut status = status.lock().unwrap();
+    for (bytes, files) in sizes {
+        status.total_bytes += bytes;
+        status.total_files += files;
+    }
+    status.state = State::Running;
+    status.started = Some(Instant::now());
+}
+
+fn copy_tree(src: &Path, dst: &Path, status: &Mutex<Status>) -> io::Result<()> {
+    if dst.starts_with(src) {
+        return Err(Error::other("copying src inside dst"));
+    }
+    // yanked out of an archive
+    match vfs::split(src) {
+        Some(_) => vfs::extract(src, dst, &mut reporter(status)).map(drop),
+        None => copy::copy(src, dst, &mut reporter(status)),
+    }
+}
+
+// same filesystem moves are just a rename, which is instant and keeps
+// hardlinks and inodes the way they were. across devices its a copy, checked
+// against the source before the source goes away
+fn move_item(src: &Path, dst: &Path, status: &Mutex<Status>) -> io::Result<()> {
+    checkpoint(status)?;
+    let same_device = match (src.symlink_metadata(), dst.parent().map(Path::metadata)) {
+        (Ok(src), Some(Ok(parent))) => src.dev() == parent.dev(),
+        _ => false,
+    };
+    if same_device {
+        match fs::rename(src, dst) {
+            Ok(_) => {
+                let (bytes, files) = measure(dst);
+                let mut status = status.lock().unwrap();
+                status.bytes += bytes;
+                status.files += files;
+                return Ok(());
+            }
+            // bind mounts and friends can share a device and still say no
+            Err(e) if e.raw_os_error() == Some(libc::EXDEV) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    copy_tree(src, dst, status)?;
+    verify(src, dst)?;
+    remove(src)
+}
+
+// checks that the copy is the same tree as the original, nothing missing and
+// nothing more, the same kind of thing, files just as long and links pointing
+// at the same place
+fn verify(src: &Path, dst: &Path) -> io::Result<()> {
+    let differs =
+        |how: &str| Error::other(format!("{}: {how}, left the original alone", dst.display()));
+    let original = src.symlink_metadata()?;
+    let copy = match dst.symlink_metadata() {
+        Ok(copy) => copy,
+        Err(_) => return Err(differs("did not get copied")),
+    };
+    if original.file_type() != copy.file_type() {
+        return Err(differs("not the same kind of file as the original"));
+    }
+    if original.is_symlink() {
+        if fs::read_link(src)? != fs::read_link(dst)? {
+            return Err(differs("points somewhere else than the original"));
+        }
+    } else if original.is_dir() {
+        for entry in fs::read_dir(src)? {
+            let entry = entry?;
+            verify(&entry.path(), &dst.join(entry.file_name()))?;
+        }
+        for entry in fs::read_dir(dst)? {
+            let name = entry?.file_name();
+            if src.join(&name).symlink_metadata().is_err() {
+                return Err(differs(&format!(
+                    "has {} in it which the original doesnt",
+                    name.to_string_lossy()
+                )));
+            }
+        }
+    } else if original.is_file() && original.len() != copy.len() {
+        return Err(differs(match original.len() > copy.len() {
+            true => "shorter than the original",
+            false => "longer than the original",
+        }));
+    }
+    Ok(())
+}
+
+fn remove(path: &Path) -> io::Result<()> {
+    match path.symlink_metadata()?.is_dir() {
+        true => fs::remove_dir_all(path),
+        false => fs::remove_file(path),
+    }
+}
+
+fn run(work: Work) -> Finished {
+    let status = &work.status;
+    let mut finished = Finished {
+        id: work.id,
+        label: work.label,
+        ops: vec![],
+        trashed: vec![],
+        errors: vec![],
+        canceled: false,
+        last: None,
+    };
+    match work.task {
+        Task::Paste {
+            mode,
+            items,
+            trashed,
+        } => {
+            finished.trashed = trashed;
+            start(
+                status,
+                items.iter().map(|(src, _)| match vfs::split(src) {
+                    Some(_) => vfs::measure(src),
+                    None => measure(src),
+                }),
+            );
+            for (src, dst) in items {
+                let existed = dst.symlink_metadata().is_ok();
+                let result = match mode {
+                    PasteMode::Move => move_item(&src, &dst, status),
+                    PasteMode::Copy => copy_tree(&src, &dst, status),
+                };
+                match result {
+                    Ok(_) => {
+                        finished.ops.push(match mode {
+                            PasteMode::Move => Op::Move {
+                                from: src,
+                                to: dst.to_path_buf(),
+                            },
+                            PasteMode::Copy => Op::Copy {
+                                from: src,
+                                to: dst.to_path_buf(),
+                            },
+                        });
+                        finished.last = Some(dst);
+                    }
+                    Err(e) if e.kind() == ErrorKind::Interrupted => {
+                        if !existed {
+                            let _ = remove(&dst);
+                        }
+                        finished.canceled = true;
+                        break;
+                    }
+                    Err(e) => finished.errors.push(format!("{}: {e}", src.display())),
+                }
+            }
+        }
+        Task::Extract { items } => {
+            start(
+                status,
+                items.iter().map(|(archive, _)| vfs::measure(archive)),
+            );
+            for (archive, dst) in items {
+                let existed = dst.symlink_metadata().is_ok();
+                match vfs::extract(&archive, &dst, &mut reporter(status)) {
+                    Ok(left_out) => {
+                        for skipped in left_out {
+                            let (name, why) = match &skipped {
+                                Skipped::Outside(name) => (name, "it points outside of"),
+                                Skipped::Exists(name) => (name, "it is already there in"),
+                            };
+                            finished.errors.push(format!(
+                                "{}: left out {}, {why} {}",
+                                archive.display(),
+                                name.display(),
+                                dst.display()
+                            ));
+                        }
+                        // extracting into a directory that was there already
+                        // isnt something undo can take back
+                        if !existed {
+                            finished.ops.push(Op::Extract {
+                                from: archive,
+                                to: dst.to_path_buf(),
+                            });
+                        }
+                        finished.last = Some(dst);
+                    }
+                    Err(e) if e.kind() == ErrorKind::Interrupted => {
+                        if !existed {
+                            let _ = remove(&dst);
+                        }
+                        finished.canceled = true;
+                        break;
+                    }
+                    Err(e) => finished.errors.push(format!("{}: {e}", archive.display())),
+                }
+            }
+        }
+        Task::Compress { sources, archive } => {
+            start(status, sources.iter().map(|source| measure(source)));
+            match compress::compress(&sources, &archive, &mut reporter(status)) {
+                Ok(_) => {
+                    finished.ops.push(Op::Compress {
+                        from: sources,
+                        to: archive.to_path_buf(),
+                    });
+                    finished.last = Some(archive);
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => finished.canceled = true,
+                Err(e) => finished.errors.push(format!("{}: {e}", archive.display())),
+            }
+        }
+    }
+    finished
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testdir::TestDir;
+    use std::os::unix::fs::symlink;
+
+    fn tree(name: &str) -> TestDir {
+        let root = TestDir::new(name);
+        fs::create_dir_all(root.join("dir")).unwrap();
+        fs::write(root.join("dir/file"), "twelve bytes").unwrap();
+        symlink("file", root.join("dir/link")).unwrap();
+        root
+    }
+
+    #[test]
+    fn verify_catches_bad_copies() {
+        let (src, dst) = (tree("src"), tree("dst"));
+        assert!(verify(&src, &dst).is_ok());
+        fs::write(dst.join("dir/file"), "twelve byte").unwrap();
+        assert!(verify(&src, &dst).is_err());
+        fs::write(dst.join("dir/file"), "twelve bytes").unwrap();
+        // something the original never had
+        fs::write(dst.join("dir/extra"), "s").unwrap();
+        let error = verify(&src, &dst).unwrap_err().to_string();
+        assert!(error.contains("has extra in it which the original doesnt"));
+        fs::remove_file(dst.join("dir/extra")).unwrap();
+        assert!(verify(&src, &dst).is_ok());
+        fs::remove_file(dst.join("dir/link")).unwrap();
+        symlink("elsewhere", dst.join("dir/link")).unwrap();
+        assert!(verify(&src, &dst).is_err());
+        fs::remove_file(dst.join("dir/link")).unwrap();
+        assert!(verify(&src, &dst).is_err());
+    }
+}
@@ -0,0 +1,562 @@
+// a record of what the file operations did and how to take them back, so
+// u and ctrl-r can walk through it. it is saved along with the tags
+use crate::{compress, copy_dir_all, trash, vfs};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::{self, Error, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+// how many actions are kept around
+const LIMIT: usize = 100;
+
+// what puts things in the trash when an op gets taken back, trash::trash
+// outside of the tests
+type Trasher<'a> = &'a dyn Fn(&Path) -> io::Result<trash::TrashEntry>;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum Op {
+    Rename {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    // touch and mkdir
+    Create {
+        path: PathBuf,
+        dir: bool,
+    },
+    Copy {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    Move {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    // an archive and the directory it was extracted to
+    Extract {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    Compress {
+        from: Vec<PathBuf>,
+        to: PathBuf,
+    },
+    // `trash` and `name` say where the item sits in the trash
+    Trash {
+        path: PathBuf,
+        trash: PathBuf,
+        name: String,
+    },
+    Restore {
+        path: PathBuf,
+        trash: PathBuf,
+        name: String,
+    },
+}
+
+impl Op {
+    pub fn trashed(entry: &trash::TrashEntry) -> Op {
+        Op::Trash {
+            path: entry.original.to_path_buf(),
+            trash: entry.trash.to_path_buf(),
+            name: entry.name.to_string(),
+        }
+    }
+
+    pub fn restored(entry: &trash::TrashEntry) -> Op {
+        Op::Restore {
+            path: entry.original.to_path_buf(),
+            trash: entry.trash.to_path_buf(),
+            name: entry.name.to_string(),
+        }
+    }
+
+    // the path worth selecting after the op was undone or redone
+    pub fn path(&self, undone: bool) -> &Path {
+        match self {
+            Op::Rename { from, to } | Op::Move { from, to } | Op::Copy { from, to } => match undone
+            {
+                true => from,
+                false => to,
+            },
+            Op::Extract { from, to } => match undone {
+                true => from,
+                false => to,
+            },
+            Op::Create { path, .. }
+            | Op::Trash { path, .. }
+            | Op::Restore { path, .. }
+            | Op::Compress { to: path, .. } => path,
+        }
+    }
+
+    // takes the op back. returns the op as it should be recorded for redoing,
+    // trashing again ends up under a different name for instance
+    fn undo(self, to_trash: Trasher) -> io::Result<Op> {
+        match self {
+            Op::Rename { ref from, ref to } | Op::Move { ref from, ref to } => {
+                vacant(from)?;
+                move_path(to, from)?;
+                Ok(self)
+            }
+            Op::Create { ref path, dir } => {
+                let empty = match dir {
+                    true => fs::read_dir(path)?.next().is_none(),
+                    false => path.metadata()?.len() == 0,
+                };
+                if !empty {
+                    return Err(Error::other("it has stuff in it now"));
+                }
+                match dir {
+                    true => fs::remove_dir(path)?,
+                    false => fs::remove_file(path)?,
+                }
+                Ok(self)
+            }
+            // the copy goes to the trash, just in case
+            Op::Copy { ref to, .. } | Op::Extract { ref to, .. } | Op::Compress { ref to, .. } => {
+                to_trash(to)?;
+                Ok(self)
+            }
+            Op::Trash { path, trash, name } => {
+                let entry = trash::TrashEntry {
+                    trash,
+                    name,
+                    original: path,
+                    deleted: String::new(),
+                };
+                trash::restore(&entry)?;
+                Ok(Op::trashed(&entry))
+            }
+            Op::Restore { path, .. } => Ok(Op::restored(&to_trash(&path)?)),
+        }
+    }
+
+    fn redo(self, to_trash: Trasher) -> io::Result<Op> {
+        match self {
+            Op::Rename { ref from, ref to } | Op::Move { ref from, ref to } => {
+                vacant(to)?;
+                move_path(from, to)?;
+                Ok(self)
+            }
+            Op::Create { ref path, dir } => {
+                match dir {
+                    true => fs::create_dir(path)?,
+                    false => {
+                        File::options().write(true).create_new(true).open(path)?;
+                    }
+                }
+                Ok(self)
+            }
+            Op::Copy { ref from, ref to } => {
+                vacant(to)?;
+                copy_dir_all(from, to)?;
+                Ok(self)
+            }
+            // redoing these happens right here, not as a job
+            Op::Extract { ref from, ref to } => {
+                vacant(to)?;
+                vfs::extract(from, to, &mut |_| Ok(()))?;
+                Ok(self)
+            }
+            Op::Compress { ref from, ref to } => {
+                vacant(to)?;
+                compress::compress(from, to, &mut |_| Ok(()))?;
+                Ok(self)
+            }
+            Op::Trash { path, .. } => Ok(Op::trashed(&to_trash(&path)?)),
+            Op::Restore { path, trash, name } => {
+                let entry = trash::TrashEntry {
+                    trash,
+                    name,
+                    original: path,
+                    deleted: String::new(),
+                };
+                trash::restore(&entry)?;
+                Ok(Op::restored(&entry))
+            }
+        }
+    }
+}
+
+// nothing gets overwritten while walking the journal
+fn vacant(path: &Path) -> io::Result<()> {
+    match path.symlink_metadata() {
+        Ok(_) => Err(Error::new(ErrorKind::AlreadyExists, "already exists")),
+        Err(_) => Ok(()),
+    }
+}
+
+fn move_path(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Ok(_) => Ok(()),
+        // another filesystem
+        Err(e) if e.raw_os_error() == Some(libc::EXDEV) => {
+            copy_dir_all(from, to)?;
+            match from.symlink_metadata()?.is_dir() {
+                true => fs::remove_dir_all(from),
+                false => fs::remove_file(from),
+            }
+        }
+        Err(e) => Err(e),
+    }
+}
+
+// everything one action did, a paste of 10 files is one entry with 10 ops
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Entry {
+    pub ops: Vec<Op>,
+}
+
+// an empty list would be a plain value after a table, which the toml confy
+// writes cant do, so those are left out
+#[derive(Serialize, Deserialize, Default)]
+pub struct Journal {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    undo: Vec<Entry>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    redo: Vec<Entry>,
+}
+
+// what walking the journal did
+pub struct Walked {
+    pub done: usize,
+    pub failed: Vec<String>,
+    // the last path that got touched
+    pub path: Option<PathBuf>,
+}
+
+impl Journal {
+    pub fn record(&mut self, ops: Vec<Op>) {
+        if ops.is_empty() {
+            return;
+        }
+        self.undo.push(Entry { ops });
+        if self.undo.len() > LIMIT {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+
+    pub fn undo(&mut self) -> Option<Walked> {
+        self.step(true, &trash::trash)
+    }
+
+    pub fn redo(&mut self) -> Option<Walked> {
+        self.step(false, &trash::trash)
+    }
+
+    fn step(&mut self, undo: bool, to_trash: Trasher) -> Option<Walked> {
+        let (from, to) = match undo {
+            true => (&mut self.undo, &mut self.redo),
+            false => (&mut self.redo, &mut self.undo),
+        };
+        let mut ops = from.pop()?.ops;
+        // undoing goes backwards, redo goes through them in the original
+        // order again
+        if undo {
+            ops.reverse();
+        }
+        let (walked, mut ops) = walk(ops, undo, to_trash);
+        if undo {
+            ops.reverse();
+        }
+        if !ops.is_empty() {
+            to.push(Entry { ops });
+        }
+        Some(walked)
+    }
+}
+
+// ops that fail are dropped, theres no taking them back anyway
+fn walk(ops: Vec<Op>, undo: bool, to_trash: Trasher) -> (Walked, Vec<Op>) {
+    let mut walked = Walked {
+        done: 0,
+        failed: vec![],
+        path: None,
+    };
+    let mut done = vec![];
+    for op in ops {
+        let path = op.path(undo).to_path_buf();
+        let result = match undo {
+            true => op.undo(to_trash),
+            false => op.redo(to_trash),
+        };
+        match result {
+            Ok(op) => {
+                walked.done += 1;
+                walked.path = Some(op.path(undo).to_path_buf());
+                done.push(op);
+            }
+            Err(e) => walked.failed.push(format!("{}: {e}", path.display())),
+        }
+    }
+    (walked, done)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testdir::TestDir;
+
+    // the trash in the test directory, so nothing ends up in the real one
+    fn in_trash(dir: &Path) -> impl Fn(&Path) -> io::Result<trash::TrashEntry> + '_ {
+        |path| trash::trash_in(path, dir.join("trash"), None)
+    }
+
+    fn step(journal: &mut Journal, dir: &Path, undo: bool) -> Walked {
+        journal.step(undo, &in_trash(dir)).unwrap()
+    }
+
+    fn trashed(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir.join("trash").join("files"))
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn renames_and_moves() {
+        let dir = TestDir::new("journal-rename");
+        fs::write(dir.join("a"), "a").unwrap();
+        fs::create_dir(dir.join("sub")).unwrap();
+        fs::write(dir.join("sub").join("b"), "b").unwrap();
+        let mut journal = Journal::default();
+        let ops = vec![
+            Op::Rename {
+                from: dir.join("a"),
+                to: dir.join("c"),
+            },
+            Op::Move {
+                from: dir.join("sub").join("b"),
+                to: dir.join("b"),
+            },
+        ];
+        for op in &ops {
+            op.clone().redo(&in_trash(&dir)).unwrap();
+        }
+        journal.record(ops);
+        let walked = step(&mut journal, &dir, true);
+        assert_eq!((walked.done, walked.failed.len()), (2, 0));
+        // the first op is the last one taken back
+        assert_eq!(walked.path, Some(dir.join("a")));
+        assert_eq!(fs::read_to_string(dir.join("a")).unwrap(), "a");
+        assert_eq!(fs::read_to_string(dir.join("sub").join("b")).unwrap(), "b");
+        assert!(!dir.join("c").exists() && !dir.join("b").exists());
+        let walked = step(&mut journal, &dir, false);
+        assert_eq!(walked.done, 2);
+        assert_eq!(walked.path, Some(dir.join("b")));
+        assert_eq!(fs::read_to_string(dir.join("c")).unwrap(), "a");
+        assert_eq!(fs::read_to_string(dir.join("b")).unwrap(), "b");
+    }
+
+    #[test]
+    fn creates_only_go_away_empty() {
+        let dir = TestDir::new("journal-create");
+        let file = dir.join("file");
+        let folder = dir.join("folder");
+        let mut journal = Journal::default();
+        let ops = vec![
+            Op::Create {
+                path: file.to_path_buf(),
+                dir: false,
+            },
+            Op::Create {
+                path: folder.to_path_buf(),
+                dir: true,
+            },
+        ];
+        for op in &ops {
+            op.clone().redo(&in_trash(&dir)).unwrap();
+        }
+        journal.record(ops);
+        assert_eq!(step(&mut journal, &dir, true).done, 2);
+        assert!(!file.exists() && !folder.exists());
+        assert_eq!(step(&mut journal, &dir, false).done, 2);
+        assert!(file.is_file() && folder.is_dir());
+        // once theres something in them they stay
+        fs::write(&file, "stuff").unwrap();
+        fs::write(folder.join("x"), "x").unwrap();
+        let walked = step(&mut journal, &dir, true);
+        assert_eq!((walked.done, walked.failed.len()), (0, 2));
+        assert!(walked.failed[0].ends_with("it has stuff in it now"));
+        assert!(file.is_file() && folder.is_dir());
+        // failed ops dont come back around for redoing
+        assert!(journal.redo.is_empty());
+    }
+
+    #[test]
+    fn copies_extracts_and_compresses_go_to_the_trash() {
+        let dir = TestDir::new("journal-copy");
+        let original = dir.join("a");
+        fs::write(&original, "a").unwrap();
+        let copy = dir.join("b");
+        let archive = dir.join("a.tar");
+        let extracted = dir.join("out");
+        let mut journal = Journal::default();
+        let ops = vec![
+            Op::Copy {
+                from: original.to_path_buf(),
+                to: copy.to_path_buf(),
+            },
+            Op::Compress {
+                from: vec![original.to_path_buf()],
+                to: archive.to_path_buf(),
+            },
+            Op::Extract {
+                from: archive.to_path_buf(),
+                to: extracted.to_path_buf(),
+            },
+        ];
+        for op in &ops {
+            op.clone().redo(&in_trash(&dir)).unwrap();
+        }
+        journal.record(ops);
+        assert_eq!(step(&mut journal, &dir, true).done, 3);
+        assert_eq!(trashed(&dir), ["a.tar", "b", "out"]);
+        assert!(!copy.exists() && !archive.exists() && !extracted.exists());
+        assert!(original.exists());
+        let walked = step(&mut journal, &dir, false);
+        assert_eq!((walked.done, walked.failed.len()), (3, 0));
+        assert_eq!(fs::read_to_string(&copy).unwrap(), "a");
+        assert_eq!(fs::read_to_string(extracted.join("a")).unwrap(), "a");
+    }
+
+    #[test]
+    fn trash_and_restore() {
+        let dir = TestDir::new("journal-trash");
+        let path = dir.join("a");
+        fs::write(&path, "a").unwrap();
+        let entry = trash::trash_in(&path, dir.join("trash"), None).unwrap();
+        let mut journal = Journal::default();
+        journal.record(vec![Op::trashed(&entry)]);
+        assert_eq!(step(&mut journal, &dir, true).done, 1);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "a");
+        assert!(trashed(&dir).is_empty());
+        assert_eq!(step(&mut journal, &dir, false).done, 1);
+        assert!(!path.exists());
+        assert_eq!(trashed(&dir), ["a"]);
+        // restoring from the trash view is the same thing the other way round
+        let entry = match &journal.undo[0].ops[0] {
+            Op::Trash { path, trash, name } => trash::TrashEntry {
+                trash: trash.to_path_buf(),
+                name: name.to_string(),
+                original: path.to_path_buf(),
+                deleted: String::new(),
+            },
+            _ => unreachable!(),
+        };
+        trash::restore(&entry).unwrap();
+        journal.record(vec![Op::restored(&entry)]);
+        assert_eq!(step(&mut journal, &dir, true).done, 1);
+        assert!(!path.exists());
+        assert_eq!(trashed(&dir), ["a"]);
+        assert_eq!(step(&mut journal, &dir, false).done, 1);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "a");
+    }
+
+    #[test]
+    fn replacing_pastes_come_back_in_one_go() {
+        let dir = TestDir::new("journal-replace");
+        fs::write(dir.join("a"), "new").unwrap();
+        fs::create_dir(dir.join("to")).unwrap();
+        let replaced = dir.join("to").join("a");
+        fs::write(&replaced, "old").unwrap();
+        let entry = trash::trash_in(&replaced, dir.join("trash"), None).unwrap();
+        let copy = Op::Copy {
+            from: dir.join("a"),
+            to: replaced.to_path_buf(),
+        };
+        copy.clone().redo(&in_trash(&dir)).unwrap();
+        let mut journal = Journal::default();
+        journal.record(vec![Op::trashed(&entry), copy]);
+        let walked = step(&mut journal, &dir, true);
+        assert_eq!((walked.done, walked.failed.len()), (2, 0));
+        assert_eq!(fs::read_to_string(&replaced).unwrap(), "old");
+        // the copy went in while the old one was still in there
+        assert_eq!(trashed(&dir), ["a.2"]);
+        let walked = step(&mut journal, &dir, false);
+        assert_eq!((walked.done, walked.failed.len()), (2, 0));
+        assert_eq!(fs::read_to_string(&replaced).unwrap(), "new");
+        assert_eq!(trashed(&dir), ["a", "a.2"]);
+    }
+
+    #[test]
+    fn nothing_gets_overwritten() {
+        let dir = TestDir::new("journal-vacant");
+        fs::write(dir.join("b"), "renamed").unwrap();
+        fs::write(dir.join("a"), "new").unwrap();
+        let mut journal = Journal::default();
+        journal.record(vec![Op::Rename {
+            from: dir.join("a"),
+            to: dir.join("b"),
+        }]);
+        let walked = step(&mut journal, &dir, true);
+        assert_eq!((walked.done, walked.failed.len()), (0, 1));
+        assert!(walked.failed[0].ends_with("already exists"));
+        assert_eq!(fs::read_to_string(dir.join("a")).unwrap(), "new");
+        assert_eq!(fs::read_to_string(dir.join("b")).unwrap(), "renamed");
+        assert!(vacant(&dir.join("c")).is_ok());
+        assert_eq!(
+            vacant(&dir.join("a")).unwrap_err().kind(),
+            ErrorKind::AlreadyExists
+        );
+    }
+
+    fn created(n: usize) -> Vec<Op> {
+        vec![Op::Create {
+            path: PathBuf::from(format!("/x/{n}")),
+            dir: false,
+        }]
+    }
+
+    #[test]
+    fn keeps_the_last_hundred() {
+        let mut journal = Journal::default();
+        journal.redo.push(Entry { ops: created(0) });
+        for n in 1..=LIMIT + 1 {
+            journal.record(created(n));
+        }
+        assert_eq!(journal.undo.len(), LIMIT);
+        assert_eq!(journal.undo[0].ops, created(2));
+        assert_eq!(journal.undo[LIMIT - 1].ops, created(LIMIT + 1));
+        // doing something new is the end of redoing
+        assert!(journal.redo.is_empty());
+        // and doing nothing isnt anything
+        journal.record(vec![]);
+        assert_eq!(journal.undo.len(), LIMIT);
+    }
+
+    #[test]
+    fn survives_a_restart() {
+        let dir = TestDir::new("journal-saved");
+        let file = dir.join("journal.toml");
+        // an empty one has to make it through too
+        confy::store_path(&file, Journal::default()).unwrap();
+        let loaded: Journal = confy::load_path(&file).unwrap();
+        assert!(loaded.undo.is_empty() && loaded.redo.is_empty());
+        let mut journal = Journal::default();
+        journal.record(vec![
+            Op::Compress {
+                from: vec![dir.join("a"), dir.join("b c")],
+                to: dir.join("a.tar"),
+            },
+            Op::Trash {
+                path: dir.join("a"),
+                trash: dir.join("trash"),
+                name: "a.2".to_string(),
+            },
+        ]);
+        journal.record(created(1));
+        journal.redo.push(Entry { ops: created(2) });
+        confy::store_path(&file, &journal).unwrap();
+        let loaded: Journal = confy::load_path(&file).unwrap();
+        assert_eq!(loaded.undo, journal.undo);
+        assert_eq!(loaded.redo, journal.redo);
+    }
+}
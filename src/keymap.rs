@@ -0,0 +1,821 @@
+// which keys do what. every mode has a table of key sequences and the action
+// they run, the defaults below are how lga always worked and the [keymap]
+// part of the config goes on top of them. keys are written like in vim:
+// `q`, `dD`, `<C-r>`, `<S-Tab>`, `<Space>`
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Mode {
+    Normal,
+    Select,
+    Input,
+    // the lists like :jobs and :trash-list
+    Panel,
+    // a question waiting for an answer, unbound keys are the answer
+    Confirm,
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Mode::Normal => "normal",
+            Mode::Select => "select",
+            Mode::Input => "input",
+            Mode::Panel => "panel",
+            Mode::Confirm => "confirm",
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    Quit,
+    GoLeft,
+    GoRight,
+    GoUp,
+    GoDown,
+    GoTop,
+    GoBottom,
+    ScrollHalfDown,
+    ScrollHalfUp,
+    ScrollDown,
+    ScrollUp,
+    ToggleWrap,
+    ToggleLineNumbers,
+    ToggleHidden,
+    Trash,
+    Delete,
+    Cut,
+    Yank,
+    Paste,
+    SortName,
+    SortNameReverse,
+    SortCreated,
+    SortCreatedReverse,
+    SortModified,
+    SortModifiedReverse,
+    SortDirsFirst,
+    SortFilesFirst,
+    Rename,
+    Command,
+    Tag,
+    TagCount,
+    Undo,
+    Redo,
+    Search,
+    SearchNext,
+    SearchPrevious,
+    Find,
+    Filter,
+    Select,
+    ToggleParent,
+    TogglePreview,
+    ToggleSingle,
+    WidenParent,
+    NarrowParent,
+    WidenPreview,
+    NarrowPreview,
+    ResetColumns,
+    ToggleDetails,
+    PauseJob,
+    CancelJob,
+    Cancel,
+    Execute,
+    DeleteChar,
+    NextCandidate,
+    PreviousCandidate,
+}
+
+// what actions are called in the config
+const ACTIONS: [(&str, Action); 55] = [
+    ("quit", Action::Quit),
+    ("go-left", Action::GoLeft),
+    ("go-right", Action::GoRight),
+    ("go-up", Action::GoUp),
+    ("go-down", Action::GoDown),
+    ("go-top", Action::GoTop),
+    ("go-bottom", Action::GoBottom),
+    ("scroll-half-down", Action::ScrollHalfDown),
+    ("scroll-half-up", Action::ScrollHalfUp),
+    ("scroll-down", Action::ScrollDown),
+    ("scroll-up", Action::ScrollUp),
+    ("toggle-wrap", Action::ToggleWrap),
+    ("toggle-line-numbers", Action::ToggleLineNumbers),
+    ("toggle-hidden", Action::ToggleHidden),
+    ("trash", Action::Trash),
+    ("delete", Action::Delete),
+    ("cut", Action::Cut),
+    ("yank", Action::Yank),
+    ("paste", Action::Paste),
+    ("sort-name", Action::SortName),
+    ("sort-name-reverse", Action::SortNameReverse),
+    ("sort-created", Action::SortCreated),
+    ("sort-created-reverse", Action::SortCreatedReverse),
+    ("sort-modified", Action::SortModified),
+    ("sort-modified-reverse", Action::SortModifiedReverse),
+    ("sort-dirs-first", Action::SortDirsFirst),
+    ("sort-files-first", Action::SortFilesFirst),
+    ("rename", Action::Rename),
+    ("command", Action::Command),
+    ("tag", Action::Tag),
+    ("tag-count", Action::TagCount),
+    ("undo", Action::Undo),
+    ("redo", Action::Redo),
+    ("search", Action::Search),
+    ("search-next", Action::SearchNext),
+    ("search-previous", Action::SearchPrevious),
+    ("find", Action::Find),
+    ("filter", Action::Filter),
+    ("select", Action::Select),
+    ("toggle-parent", Action::ToggleParent),
+    ("toggle-preview", Action::TogglePreview),
+    ("toggle-single", Action::ToggleSingle),
+    ("widen-parent", Action::WidenParent),
+    ("narrow-parent", Action::NarrowParent),
+    ("widen-preview", Action::WidenPreview),
+    ("narrow-preview", Action::NarrowPreview),
+    ("reset-columns", Action::ResetColumns),
+    ("toggle-details", Action::ToggleDetails),
+    ("pause-job", Action::PauseJob),
+    ("cancel-job", Action::CancelJob),
+    ("cancel", Action::Cancel),
+    ("execute", Action::Execute),
+    ("delete-char", Action::DeleteChar),
+    ("next-candidate", Action::NextCandidate),
+    ("previous-candidate", Action::PreviousCandidate),
+];
+
+// binding a key to this takes the default away
+const NONE: &str = "none";
+
+impl Action {
+    pub fn parse(name: &str) -> Option<Action> {
+        ACTIONS
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, action)| *action)
+    }
+
+    pub fn name(&self) -> &'static str {
+        ACTIONS
+            .iter()
+            .find(|(_, action)| action == self)
+            .map(|(name, _)| *name)
+            .unwrap_or_default()
+    }
+
+    // not every action makes sense everywhere
+    pub fn works_in(&self, mode: Mode) -> bool {
+        use Action::*;
+        match mode {
+            Mode::Input => matches!(
+                self,
+                Execute | Cancel | DeleteChar | NextCandidate | PreviousCandidate
+            ),
+            Mode::Select => matches!(
+                self,
+                Quit | Select
+                    | GoLeft
+                    | GoRight
+                    | GoUp
+                    | GoDown
+                    | Cancel
+                    | ToggleHidden
+                    | Command
+                    | Cut
+                    | Trash
+                    | Delete
+                    | Yank
+            ),
+            Mode::Panel => matches!(
+                self,
+                GoUp | GoDown | GoTop | GoBottom | Execute | Cancel | PauseJob | CancelJob
+            ),
+            Mode::Confirm => matches!(self, Cancel),
+            Mode::Normal => !matches!(
+                self,
+                Cancel
+                    | Execute
+                    | DeleteChar
+                    | NextCandidate
+                    | PreviousCandidate
+                    | PauseJob
+                    | CancelJob
+            ),
+        }
+    }
+}
+
+const NORMAL: [(&str, Action); 56] = [
+    ("q", Action::Quit),
+    ("l", Action::GoRight),
+    ("<Right>", Action::GoRight),
+    ("<Enter>", Action::GoRight),
+    ("k", Action::GoUp),
+    ("<Up>", Action::GoUp),
+    ("j", Action::GoDown),
+    ("<Down>", Action::GoDown),
+    ("h", Action::GoLeft),
+    ("<Left>", Action::GoLeft),
+    ("g", Action::GoTop),
+    ("<PageUp>", Action::GoTop),
+    ("G", Action::GoBottom),
+    ("<PageDown>", Action::GoBottom),
+    ("<C-d>", Action::ScrollHalfDown),
+    ("<C-u>", Action::ScrollHalfUp),
+    ("J", Action::ScrollDown),
+    ("K", Action::ScrollUp),
+    ("zw", Action::ToggleWrap),
+    ("zn", Action::ToggleLineNumbers),
+    ("dD", Action::Trash),
+    ("dX", Action::Delete),
+    ("dd", Action::Cut),
+    ("yy", Action::Yank),
+    ("Yy", Action::Yank),
+    ("p", Action::Paste),
+    ("sn", Action::SortName),
+    ("sN", Action::SortNameReverse),
+    ("sc", Action::SortCreated),
+    ("sC", Action::SortCreatedReverse),
+    ("sm", Action::SortModified),
+    ("sM", Action::SortModifiedReverse),
+    ("sd", Action::SortDirsFirst),
+    ("sf", Action::SortFilesFirst),
+    ("a", Action::Rename),
+    (":", Action::Command),
+    ("<BS>", Action::ToggleHidden),
+    ("t", Action::Tag),
+    ("T", Action::TagCount),
+    ("u", Action::Undo),
+    ("<C-r>", Action::Redo),
+    ("/", Action::Search),
+    ("n", Action::SearchNext),
+    ("N", Action::SearchPrevious),
+    ("f", Action::Find),
+    ("F", Action::Filter),
+    ("<Space>", Action::Select),
+    ("zh", Action::ToggleParent),
+    ("zl", Action::TogglePreview),
+    ("zs", Action::ToggleSingle),
+    ("}", Action::WidenParent),
+    ("{", Action::NarrowParent),
+    ("]", Action::WidenPreview),
+    ("[", Action::NarrowPreview),
+    ("=", Action::ResetColumns),
+    ("zd", Action::ToggleDetails),
+];
+
+const SELECT: [(&str, Action); 17] = [
+    ("q", Action::Quit),
+    ("<Space>", Action::Select),
+    ("h", Action::GoLeft),
+    ("<Left>", Action::GoLeft),
+    ("l", Action::GoRight),
+    ("<Right>", Action::GoRight),
+    ("k", Action::GoUp),
+    ("<Up>", Action::GoUp),
+    ("j", Action::GoDown),
+    ("<Down>", Action::GoDown),
+    ("<Esc>", Action::Cancel),
+    ("<BS>", Action::ToggleHidden),
+    (":", Action::Command),
+    ("d", Action::Cut),
+    ("D", Action::Trash),
+    ("X", Action::Delete),
+    ("y", Action::Yank),
+];
+
+const INPUT: [(&str, Action); 7] = [
+    ("<Enter>", Action::Execute),
+    ("<Esc>", Action::Cancel),
+    ("<BS>", Action::DeleteChar),
+    ("<Tab>", Action::NextCandidate),
+    ("<Down>", Action::NextCandidate),
+    ("<S-Tab>", Action::PreviousCandidate),
+    ("<Up>", Action::PreviousCandidate),
+];
+
+const PANEL: [(&str, Action); 12] = [
+    ("j", Action::GoDown),
+    ("<Down>", Action::GoDown),
+    ("k", Action::GoUp),
+    ("<Up>", Action::GoUp),
+    ("g", Action::GoTop),
+    ("G", Action::GoBottom),
+    ("l", Action::Execute),
+    ("<Enter>", Action::Execute),
+    ("q", Action::Cancel),
+    ("<Esc>", Action::Cancel),
+    ("p", Action::PauseJob),
+    ("x", Action::CancelJob),
+];
+
+const CONFIRM: [(&str, Action); 1] = [("<Esc>", Action::Cancel)];
+
+// a key with its modifiers. shift is part of the char already, uppercase or
+// not, so chars never carry it. with control its always lowercase, like
+// terminals send it
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Key {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl From<KeyEvent> for Key {
+    fn from(event: KeyEvent) -> Key {
+        let modifiers = match event.code {
+            KeyCode::Char(_) | KeyCode::BackTab => event.modifiers - KeyModifiers::SHIFT,
+            _ => event.modifiers,
+        };
+        Key {
+            code: event.code,
+            modifiers,
+        }
+        .normalize()
+    }
+}
+
+impl Key {
+    fn normalize(mut self) -> Key {
+        if let (KeyCode::Char(c), true) =
+            (self.code, self.modifiers.contains(KeyModifiers::CONTROL))
+        {
+            self.code = KeyCode::Char(c.to_ascii_lowercase());
+        }
+        self
+    }
+
+    // what gets typed when this key isnt bound to anything in input mode
+    pub fn char(&self) -> Option<char> {
+        match self.code {
+            KeyCode::Char(c)
+                if !self
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                Some(c)
+            }
+            _ => None,
+        }
+    }
+}
+
+// names for the keys that arent a char, the first one is how they get shown
+const NAMES: [(&str, KeyCode); 21] = [
+    ("Enter", KeyCode::Enter),
+    ("CR", KeyCode::Enter),
+    ("Esc", KeyCode::Esc),
+    ("Tab", KeyCode::Tab),
+    ("BS", KeyCode::Backspace),
+    ("Backspace", KeyCode::Backspace),
+    ("Space", KeyCode::Char(' ')),
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("PageUp", KeyCode::PageUp),
+    ("PageDown", KeyCode::PageDown),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("Del", KeyCode::Delete),
+    ("Delete", KeyCode::Delete),
+    ("Insert", KeyCode::Insert),
+    ("lt", KeyCode::Char('<')),
+    ("gt", KeyCode::Char('>')),
+    ("S-Tab", KeyCode::BackTab),
+];
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = NAMES
+            .iter()
+            .find(|(_, code)| *code == self.code)
+            .map(|(name, _)| name.to_string());
+        let name = match (self.code, name) {
+            (_, Some(name)) => name,
+            (KeyCode::F(n), _) => format!("F{n}"),
+            (KeyCode::Char(c), _) => c.to_string(),
+            (code, _) => format!("{code:?}"),
+        };
+        let mut prefix = String::new();
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            prefix.push_str("C-");
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            prefix.push_str("A-");
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            prefix.push_str("S-");
+        }
+        match prefix.is_empty() && name.chars().count() == 1 {
+            true => write!(f, "{name}"),
+            false => write!(f, "<{prefix}{name}>"),
+        }
+    }
+}
+
+fn named(name: &str) -> Option<KeyCode> {
+    NAMES
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, code)| *code)
+}
+
+// whats between < and >, like C-r or PageUp
+fn parse_special(special: &str) -> Option<Key> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut rest = special;
+    // <C--> is control and a dash, so the last char is never a modifier.
+    // S-Tab has a name of its own
+    while named(rest).is_none() && rest.len() > 2 && rest.as_bytes()[1] == b'-' {
+        modifiers |= match rest.as_bytes()[0].to_ascii_uppercase() {
+            b'C' => KeyModifiers::CONTROL,
+            b'A' | b'M' => KeyModifiers::ALT,
+            b'S' => KeyModifiers::SHIFT,
+            _ => return None,
+        };
+        rest = &rest[2..];
+    }
+    let mut chars = rest.chars();
+    let code = match (named(rest), chars.next(), chars.next()) {
+        (Some(code), _, _) => code,
+        (None, Some(c), None) => KeyCode::Char(c),
+        (None, Some('F' | 'f'), Some(_)) => KeyCode::F(rest[1..].parse().ok()?),
+        _ => return None,
+    };
+    let mut key = Key { code, modifiers };
+    // shift goes into the char, and <S-Tab> is what terminals call backtab
+    if key.modifiers.contains(KeyModifiers::SHIFT) {
+        match key.code {
+            KeyCode::Char(c) => key.code = KeyCode::Char(c.to_ascii_uppercase()),
+            KeyCode::Tab => key.code = KeyCode::BackTab,
+            _ => return Some(key),
+        }
+        key.modifiers -= KeyModifiers::SHIFT;
+    }
+    Some(key.normalize())
+}
+
+// `dD`, `<C-w>h` and so on
+pub fn parse(sequence: &str) -> Option<Vec<Key>> {
+    let mut keys = vec![];
+    let mut rest = sequence;
+    while let Some(c) = rest.chars().next() {
+        match (c, rest.find('>')) {
+            ('<', Some(end)) if end > 1 => {
+                keys.push(parse_special(&rest[1..end])?);
+                rest = &rest[end + 1..];
+            }
+            _ => {
+                keys.push(Key {
+                    code: KeyCode::Char(c),
+                    modifiers: KeyModifiers::NONE,
+                });
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    match keys.is_empty() {
+        true => None,
+        false => Some(keys),
+    }
+}
+
+pub fn show(keys: &[Key]) -> String {
+    keys.iter().map(Key::to_string).collect()
+}
+
+// the [keymap] part of the config, key sequences to action names for each
+// mode. `none` takes a default away
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct KeymapConfig {
+    pub normal: BTreeMap<String, String>,
+    pub select: BTreeMap<String, String>,
+    pub input: BTreeMap<String, String>,
+    pub panel: BTreeMap<String, String>,
+    pub confirm: BTreeMap<String, String>,
+}
+
+pub enum Lookup {
+    Action(Action),
+    // more keys are needed
+    Prefix,
+    None,
+}
+
+pub struct Keymap {
+    normal: Vec<(Vec<Key>, Action)>,
+    select: Vec<(Vec<Key>, Action)>,
+    input: Vec<(Vec<Key>, Action)>,
+    panel: Vec<(Vec<Key>, Action)>,
+    confirm: Vec<(Vec<Key>, Action)>,
+}
+
+// one mode worth of bindings, the defaults with the config on top. whatever
+// is wrong with the config ends up in problems
+fn bind(
+    mode: Mode,
+    defaults: &[(&str, Action)],
+    config: &BTreeMap<String, String>,
+    problems: &mut Vec<String>,
+) -> Vec<(Vec<Key>, Action)> {
+    let mut bindings: Vec<(Vec<Key>, Action)> = defaults
+        .iter()
+        .filter_map(|(keys, action)| Some((parse(keys)?, *action)))
+        .collect();
+    // what the config binds, with how it was written
+    let mut own: Vec<(Vec<Key>, &str, Option<Action>)> = vec![];
+    for (sequence, name) in config {
+        let keys = match parse(sequence) {
+            Some(keys) => keys,
+            None => {
+                problems.push(format!("cant read the keys `{sequence}` in {mode} mode"));
+                continue;
+            }
+        };
+        let action = match (name.as_str(), Action::parse(name)) {
+            (NONE, _) => None,
+            (_, Some(action)) if action.works_in(mode) => Some(action),
+            (_, Some(_)) => {
+                problems.push(format!("{name} does nothing in {mode} mode"));
+                continue;
+            }
+            (_, None) => {
+                problems.push(format!(
+                    "{name} (on `{sequence}`) is not a thing lga can do"
+                ));
+                continue;
+            }
+        };
+        if let Some((_, other, _)) = own.iter().find(|(k, _, _)| *k == keys) {
+            problems.push(format!(
+                "`{other}` and `{sequence}` are the same keys in {mode} mode"
+            ));
+            continue;
+        }
+        own.push((keys, sequence, action));
+    }
+    // a sequence that starts with another one can never be typed, the
+    // shorter one goes off first
+    for (keys, sequence, _) in own.iter().filter(|(_, _, action)| action.is_some()) {
+        for (other, other_sequence, _) in &own {
+            if other.len() > keys.len() && other.starts_with(keys) {
+                problems.push(format!(
+                    "`{sequence}` hides `{other_sequence}` in {mode} mode"
+                ));
+            }
+        }
+    }
+    // the config wins over the defaults it runs into. the same keys is just
+    // rebinding them, but a default that starts with or is the start of a
+    // binding goes away without that being obvious, so that gets said
+    for (keys, sequence, action) in &own {
+        let taken: Vec<String> = bindings
+            .iter()
+            .filter(|(k, _)| k != keys && (k.starts_with(keys) || keys.starts_with(k)))
+            .filter(|(k, _)| !own.iter().any(|(other, _, _)| other == k))
+            .map(|(k, action)| format!("`{}` ({})", show(k), action.name()))
+            .collect();
+        if action.is_some() && !taken.is_empty() {
+            problems.push(format!(
+                "`{sequence}` takes away {} in {mode} mode",
+                taken.join(", ")
+            ));
+        }
+    }
+    bindings.retain(|(keys, _)| {
+        !own.iter()
+            .any(|(k, _, _)| k.starts_with(keys) || keys.starts_with(k))
+    });
+    for (keys, _, action) in &own {
+        let hidden = own
+            .iter()
+            .any(|(k, _, a)| a.is_some() && k.len() < keys.len() && keys.starts_with(k));
+        if let (Some(action), false) = (action, hidden) {
+            bindings.push((keys.to_vec(), *action));
+        }
+    }
+    bindings
+}
+
+impl Keymap {
+    // the keymap and whatever was wrong with the config
+    pub fn new(config: &KeymapConfig) -> (Keymap, Vec<String>) {
+        let mut problems = vec![];
+        let keymap = Keymap {
+            normal: bind(Mode::Normal, &NORMAL, &config.normal, &mut problems),
+            select: bind(Mode::Select, &SELECT, &config.select, &mut problems),
+            input: bind(Mode::Input, &INPUT, &config.input, &mut problems),
+            panel: bind(Mode::Panel, &PANEL, &config.panel, &mut problems),
+            confirm: bind(Mode::Confirm, &CONFIRM, &config.confirm, &mut problems),
+        };
+        (keymap, problems)
+    }
+
+    fn bindings(&self, mode: Mode) -> &[(Vec<Key>, Action)] {
+        match mode {
+            Mode::Normal => &self.normal,
+            Mode::Select => &self.select,
+            Mode::Input => &self.input,
+            Mode::Panel => &self.panel,
+            Mode::Confirm => &self.confirm,
+        }
+    }
+
+    pub fn lookup(&self, mode: Mode, keys: &[Key]) -> Lookup {
+        let bindings = self.bindings(mode);
+        if let Some((_, action)) = bindings.iter().find(|(k, _)| k == keys) {
+            return Lookup::Action(*action);
+        }
+        match bindings.iter().any(|(k, _)| k.starts_with(keys)) {
+            true => Lookup::Prefix,
+            false => Lookup::None,
+        }
+    }
+
+    // the first keys that do action, for titles that tell what to press
+    pub fn keys_for(&self, mode: Mode, action: Action) -> String {
+        match self.bindings(mode).iter().find(|(_, a)| *a == action) {
+            Some((keys, _)) => show(keys),
+            None => "(no key)".to_string(),
+        }
+    }
+
+    // what can come after keys, for the message line
+    pub fn hint(&self, mode: Mode, keys: &[Key]) -> String {
+        let next: Vec<String> = self
+            .bindings(mode)
+            .iter()
+            .filter(|(k, action)| {
+                k.len() > keys.len() && k.starts_with(keys) && *action != Action::Cancel
+            })
+            .map(|(k, action)| format!("{} {}", show(&k[keys.len()..]), action.name()))
+            .collect();
+        format!("{}: {}", show(keys), next.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> Key {
+        Key { code, modifiers }
+    }
+
+    fn config(mode: &str, bindings: &[(&str, &str)]) -> KeymapConfig {
+        let mut config = KeymapConfig::default();
+        let table = match mode {
+            "normal" => &mut config.normal,
+            "panel" => &mut config.panel,
+            _ => &mut config.input,
+        };
+        for (keys, action) in bindings {
+            table.insert(keys.to_string(), action.to_string());
+        }
+        config
+    }
+
+    #[test]
+    fn plain_and_special_keys() {
+        let none = KeyModifiers::NONE;
+        assert_eq!(
+            parse("dD"),
+            Some(vec![
+                key(KeyCode::Char('d'), none),
+                key(KeyCode::Char('D'), none)
+            ])
+        );
+        assert_eq!(
+            parse("<C-w>h"),
+            Some(vec![
+                key(KeyCode::Char('w'), KeyModifiers::CONTROL),
+                key(KeyCode::Char('h'), none)
+            ])
+        );
+        assert_eq!(parse("<Space>"), Some(vec![key(KeyCode::Char(' '), none)]));
+        assert_eq!(parse("<lt>"), Some(vec![key(KeyCode::Char('<'), none)]));
+        assert_eq!(parse("<cr>"), Some(vec![key(KeyCode::Enter, none)]));
+        assert_eq!(parse("<F5>"), Some(vec![key(KeyCode::F(5), none)]));
+        assert_eq!(
+            parse("<M-x>"),
+            Some(vec![key(KeyCode::Char('x'), KeyModifiers::ALT)])
+        );
+        assert_eq!(
+            parse("<C-->"),
+            Some(vec![key(KeyCode::Char('-'), KeyModifiers::CONTROL)])
+        );
+        // a < without a > after it is just a <
+        assert_eq!(parse("<"), Some(vec![key(KeyCode::Char('<'), none)]));
+    }
+
+    #[test]
+    fn shift_and_control_are_normalized() {
+        assert_eq!(parse("<C-R>"), parse("<C-r>"));
+        assert_eq!(parse("<S-a>"), parse("A"));
+        assert_eq!(
+            parse("<S-Tab>"),
+            Some(vec![key(KeyCode::BackTab, KeyModifiers::NONE)])
+        );
+        assert_eq!(parse("<s-tab>"), parse("<S-Tab>"));
+        let event = KeyEvent::new(
+            KeyCode::Char('R'),
+            KeyModifiers::CONTROL | KeyModifiers::SHIFT,
+        );
+        assert_eq!(Some(vec![Key::from(event)]), parse("<C-r>"));
+    }
+
+    #[test]
+    fn bad_keys() {
+        assert_eq!(parse(""), None);
+        assert_eq!(parse("<foo>"), None);
+        assert_eq!(parse("<X-a>"), None);
+        assert_eq!(parse("<Fx>"), None);
+        assert_eq!(
+            parse("a<>"),
+            Some(vec![
+                key(KeyCode::Char('a'), KeyModifiers::NONE),
+                key(KeyCode::Char('<'), KeyModifiers::NONE),
+                key(KeyCode::Char('>'), KeyModifiers::NONE)
+            ])
+        );
+    }
+
+    #[test]
+    fn shown_the_way_they_are_written() {
+        for sequence in [
+            "dD", "<C-r>", "<A-x>", "<S-Tab>", "<Space>", "<lt>", "<F5>", "<Enter>x",
+        ] {
+            assert_eq!(show(&parse(sequence).unwrap()), sequence);
+        }
+    }
+
+    #[test]
+    fn lookup_waits_for_the_rest() {
+        let (keymap, problems) = Keymap::new(&KeymapConfig::default());
+        assert!(problems.is_empty(), "{problems:?}");
+        let d = parse("d").unwrap();
+        assert!(matches!(keymap.lookup(Mode::Normal, &d), Lookup::Prefix));
+        assert!(matches!(
+            keymap.lookup(Mode::Normal, &parse("dd").unwrap()),
+            Lookup::Action(Action::Cut)
+        ));
+        assert!(matches!(
+            keymap.lookup(Mode::Normal, &parse("<F12>").unwrap()),
+            Lookup::None
+        ));
+        assert!(matches!(
+            keymap.lookup(Mode::Panel, &parse("x").unwrap()),
+            Lookup::Action(Action::CancelJob)
+        ));
+        assert!(matches!(
+            keymap.lookup(Mode::Confirm, &parse("y").unwrap()),
+            Lookup::None
+        ));
+    }
+
+    #[test]
+    fn taken_defaults_are_reported() {
+        let (keymap, problems) = Keymap::new(&config("normal", &[("d", "go-down")]));
+        assert_eq!(problems.len(), 1, "{problems:?}");
+        assert!(
+            problems[0] == "`d` takes away `dD` (trash), `dX` (delete), `dd` (cut) in normal mode",
+            "{problems:?}"
+        );
+        assert!(matches!(
+            keymap.lookup(Mode::Normal, &parse("d").unwrap()),
+            Lookup::Action(Action::GoDown)
+        ));
+        // rebinding the same keys, or taking one away on purpose, is quiet
+        let (_, problems) = Keymap::new(&config("normal", &[("dd", "yank"), ("t", "none")]));
+        assert!(problems.is_empty(), "{problems:?}");
+        // and so is a default the config binds again itself
+        let (_, problems) = Keymap::new(&config("panel", &[("p", "none"), ("pp", "pause-job")]));
+        assert!(
+            problems.iter().all(|p| !p.contains("takes away")),
+            "{problems:?}"
+        );
+    }
+
+    #[test]
+    fn config_mistakes_are_reported() {
+        let (_, problems) = Keymap::new(&config(
+            "normal",
+            &[("<foo>", "quit"), ("Q", "fly"), ("<C-n>", "cancel")],
+        ));
+        assert_eq!(problems.len(), 3, "{problems:?}");
+        let (_, problems) = Keymap::new(&config(
+            "input",
+            &[("<C-r>", "cancel"), ("<C-R>", "cancel")],
+        ));
+        assert!(problems[0].contains("are the same keys"), "{problems:?}");
+        let (_, problems) = Keymap::new(&config("normal", &[("Q", "quit"), ("QQ", "tag")]));
+        assert!(
+            problems.iter().any(|p| p.contains("`Q` hides `QQ`")),
+            "{problems:?}"
+        );
+    }
+}
@@ -1,56 +1,86 @@
+mod bulkrename;
+mod compress;
+mod config;
+mod copy;
+mod details;
+mod fuzzy;
+mod highlight;
+mod jobs;
+mod journal;
+mod keymap;
+mod opener;
+mod paste;
+mod preview;
+mod search;
+#[cfg(test)]
+mod testdir;
+mod theme;
+mod trash;
 mod ui;
-use confy::{load, store};
+mod vfs;
+use config::Config;
+use confy::{load_path, store_path};
 use crossterm::{
     event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use file_format::{FileFormat, Kind};
+use details::Names;
+use file_format::FileFormat;
 use humansize::{format_size, DECIMAL};
+use jobs::{Jobs, Task};
+use journal::{Journal, Op};
+use keymap::{Action, Key, Keymap, Lookup, Mode};
+use opener::Opened;
+use paste::{free_name, Conflict, Resolving};
+use preview::{Loader, Preview};
+use regex::Regex;
+use search::{Matcher, Search};
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::HashSet,
     env,
-    fs::{self, copy, create_dir, remove_dir, remove_dir_all, remove_file, rename, File},
-    io::{self, Error, ErrorKind},
+    fs::{self, create_dir, remove_dir_all, remove_file, rename, File, Metadata},
+    io::{self, Error},
     mem,
-    os::unix::prelude::MetadataExt,
     path::{Path, PathBuf},
-    process::{Command, Stdio},
-    time::SystemTime,
-    usize,
+    process::{self, Command, ExitStatus},
+    time::{Duration, SystemTime},
 };
+use theme::Theme;
+use trash::TrashEntry;
 use tui::{
     backend::{Backend, CrosstermBackend},
+    text::Text,
     widgets::ListState,
     Terminal,
 };
+use ui::Panes;
 
-#[derive(PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone)]
 struct Item<T, U> {
     path: T,
     tagged: bool,
     preview: Option<U>,
+    // taken when the directory is listed, so drawing doesnt have to stat.
+    // none inside archives
+    metadata: Option<Metadata>,
 }
 impl<T, U> Item<T, U> {
-    fn new(t: T, tagged: bool) -> Item<T, U> {
+    fn new(t: T, tagged: bool, metadata: Option<Metadata>) -> Item<T, U> {
         Item {
             path: t,
             tagged,
             preview: None,
+            metadata,
         }
     }
-    fn tag(&mut self) {
-        self.tagged = true
-    }
     fn toggle_tagged(&mut self) {
         self.tagged = !self.tagged
     }
     fn set_preview(&mut self, preview: U) {
         self.preview = Some(preview)
     }
-    fn set_item(&mut self, item: T) {
-        self.path = item
-    }
 }
 
 #[derive(Clone)]
@@ -60,7 +90,7 @@ struct StatefulList<T> {
 }
 impl<T> StatefulList<T> {
     fn next(&mut self) {
-        if self.items.len() > 0 {
+        if !self.items.is_empty() {
             let i = match self.state.selected() {
                 Some(i) => {
                     if i >= self.items.len() - 1 {
@@ -76,7 +106,7 @@ impl<T> StatefulList<T> {
     }
 
     fn prev(&mut self) {
-        if self.items.len() > 0 {
+        if !self.items.is_empty() {
             let i = match self.state.selected() {
                 Some(i) => {
                     if i == 0 {
@@ -93,15 +123,31 @@ impl<T> StatefulList<T> {
 }
 
 enum Confirm {
-    DeleteFolder,
+    // permanently, trashing doesnt ask
     DeleteSelection(Vec<PathBuf>),
+    EmptyTrash,
+    BulkRename(Vec<bulkrename::Step>),
+    // asks about one item at a time, answered with o/s/r
+    PasteConflict(Resolving),
+}
+
+enum PanelKind {
+    Trash(Vec<TrashEntry>),
+    Jobs,
+    // the hits are in app.search
+    Search,
+}
+
+// a list that pops up over the columns, like the contents of the trash
+struct Panel {
+    title: String,
+    list: StatefulList<String>,
+    kind: PanelKind,
 }
 
 enum InputMode {
     // normal mode for navigation and all
     Normal,
-    // command mode for issuing short commands, navigation still works
-    Command(String),
     // input mode: navigation doesnt work, all input gets buffered until enter
     // or esc is clicked
     Input(String),
@@ -110,51 +156,43 @@ enum InputMode {
     Confirmation(Confirm, char),
     // select mode is.. well.. for selecting stuff lol
     Select(Vec<PathBuf>),
+    // a panel is open on top of everything and gets all the keys
+    Panel(Panel),
 }
 impl InputMode {
     // gotta do better than this
     fn push_char(&mut self, c: char) {
-        match self {
-            InputMode::Command(s) | InputMode::Input(s) => s.push(c),
-            _ => {}
+        if let InputMode::Input(s) = self {
+            s.push(c)
         }
     }
     fn push_path(&mut self, p: PathBuf) {
-        match self {
-            InputMode::Select(v) => v.push(p),
-            _ => {}
+        if let InputMode::Select(v) = self {
+            v.push(p)
         }
     }
     fn pop_char(&mut self) -> Option<char> {
         match self {
-            InputMode::Command(s) | InputMode::Input(s) => s.pop(),
+            InputMode::Input(s) => s.pop(),
             _ => None,
         }
     }
     fn remove_path(&mut self, index: usize) {
-        match self {
-            InputMode::Select(v) => {
-                v.remove(index);
-            }
-            _ => {}
+        if let InputMode::Select(v) = self {
+            v.remove(index);
         }
     }
     fn get_str(&self) -> String {
         match self {
-            InputMode::Command(s) | InputMode::Input(s) => s.to_string(),
+            InputMode::Input(s) => s.to_string(),
             _ => String::new(),
         }
     }
-    fn get_selected(&self) -> Vec<PathBuf> {
-        match self {
-            InputMode::Select(s) => s.to_vec(),
-            _ => vec![],
-        }
-    }
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 enum ListOrder {
-    Default,
     Name,
     NameReverse,
     Modified,
@@ -165,16 +203,7 @@ enum ListOrder {
     FilesFirst,
 }
 
-#[derive(Serialize, Deserialize)]
-struct Config {
-    tags: Vec<PathBuf>,
-}
-impl ::std::default::Default for Config {
-    fn default() -> Self {
-        Self { tags: vec![] }
-    }
-}
-
+#[derive(Clone, Copy)]
 enum PasteMode {
     Move,
     Copy,
@@ -186,13 +215,13 @@ struct Register {
 }
 
 pub struct App {
-    left_column: StatefulList<Item<PathBuf, String>>,
-    middle_column: StatefulList<Item<PathBuf, String>>,
-    right_column: StatefulList<Item<PathBuf, String>>,
+    left_column: StatefulList<Item<PathBuf, Text<'static>>>,
+    middle_column: StatefulList<Item<PathBuf, Text<'static>>>,
+    right_column: StatefulList<Item<PathBuf, Text<'static>>>,
     // order... in the courtroom
     orderby: ListOrder,
     pwd: PathBuf,
-    // show hidden files
+    // hide dotfiles, the other way around from show_hidden in the config
     hidden: bool,
     // the things that show up on the lower left corner
     message: String,
@@ -204,32 +233,89 @@ pub struct App {
     yank_register: Register,
     // app config that gets saved
     config: Config,
+    // how everything looks, from the config and the environment
+    theme: Theme,
+    // file operations that can be undone, saved too
+    journal: Journal,
+    // program waiting to take over the terminal, run_app picks it up
+    foreground: Option<Command>,
+    // the select set, when a command was started from select mode
+    selection: Vec<PathBuf>,
+    // names being edited for :bulkrename
+    bulkrename: Option<bulkrename::Pending>,
+    // copying and moving in the background
+    jobs: Jobs,
+    // the last :search or :grep, it goes on in the background
+    search: Option<Search>,
+    // a grep hit to point out in the preview: the file, the line and what
+    // matched
+    mark: Option<(PathBuf, usize, Regex)>,
+    // the line of the mark to scroll to, once the preview is in
+    seek: Option<usize>,
+    // what :find ranked, best first, shown over the middle column while
+    // typing. by path, the middle column can change under it
+    finder: StatefulList<(PathBuf, fuzzy::Match)>,
+    // only what matches shows up in the directory the filter was set in, it
+    // stays until it gets cleared
+    filter: Option<(PathBuf, String, Matcher)>,
+    // what / looked for last, for n and N
+    last_search: Option<String>,
+    // the directory preview, goes on top of the right column
+    summary: String,
+    // makes the previews in the background
+    loader: Loader,
+    // how far down the preview is scrolled
+    scroll: u16,
+    // how many lines of preview fit, ui keeps it up to date
+    preview_height: u16,
+    wrap: bool,
+    line_numbers: bool,
+    // how wide the columns are and which show up, changed with keys
+    columns: [u16; 3],
+    panes: Panes,
+    // the long view, and who owns what for it
+    details: bool,
+    names: Names,
+    keymap: Keymap,
+    // the start of a key sequence, like the d in dD
+    keys: Vec<Key>,
 }
 
 impl App {
-    fn new(pwd: PathBuf, hidden: bool) -> App {
+    fn new(pwd: PathBuf, cfg: Config) -> App {
+        let hidden = !cfg.show_hidden;
         // we might need to display some message on start
-        let message = String::new();
-        let cfg: Config = load("lga", Some("tags")).unwrap();
+        let (keymap, problems) = Keymap::new(&cfg.keymap);
+        let message = match problems.len() {
+            0 => String::new(),
+            1 => format!("keymap: {}", problems[0]),
+            n => format!("keymap: {} (and {} more)", problems[0], n - 1),
+        };
+        let theme = Theme::new(&cfg.theme);
+        // mono means no colors in previews either, without touching the config
+        let mut previews = cfg.previews.clone();
+        if theme.plain {
+            previews.theme = highlight::NO_THEME.to_string();
+        }
         // list the parent stuff
         let left_column_items = match pwd.parent() {
-            Some(parent) => ls(parent, hidden, &ListOrder::DirsFirst, &cfg.tags),
+            Some(parent) => ls(parent, hidden, &cfg.order, &cfg.tags),
             None => vec![],
         };
         // list pwd stuff
-        let middle_column_items = ls(&pwd, hidden, &ListOrder::DirsFirst, &cfg.tags);
+        let middle_column_items = ls(&pwd, hidden, &cfg.order, &cfg.tags);
         // list child stuff
         let right_column_items = ls(
-            &middle_column_items
-                .get(0)
-                .unwrap_or(&Item::new(PathBuf::default(), false))
+            middle_column_items
+                .first()
+                .unwrap_or(&Item::new(PathBuf::default(), false, None))
                 .path
                 .as_path(),
             hidden,
-            &ListOrder::DirsFirst,
+            &cfg.order,
             &cfg.tags,
         );
-        let right_column_list_state = if right_column_items.len() > 0 {
+        let right_column_list_state = if !right_column_items.is_empty() {
             let mut state = ListState::default();
             state.select(Some(0));
             state
@@ -249,7 +335,7 @@ impl App {
                 items: right_column_items,
                 state: right_column_list_state,
             },
-            orderby: ListOrder::DirsFirst,
+            orderby: cfg.order,
             pwd: pwd.to_path_buf(),
             hidden,
             message,
@@ -259,6 +345,33 @@ impl App {
                 register: Vec::new(),
                 mode: PasteMode::Copy,
             },
+            journal: Journal::default(),
+            foreground: None,
+            selection: vec![],
+            bulkrename: None,
+            jobs: Jobs::new(),
+            search: None,
+            mark: None,
+            seek: None,
+            finder: StatefulList {
+                items: vec![],
+                state: ListState::default(),
+            },
+            filter: None,
+            last_search: None,
+            summary: String::new(),
+            loader: Loader::new(previews),
+            scroll: 0,
+            preview_height: 0,
+            wrap: false,
+            line_numbers: false,
+            columns: cfg.columns,
+            panes: cfg.panes,
+            details: cfg.details.show,
+            names: Names::default(),
+            keymap,
+            keys: vec![],
+            theme,
             config: cfg,
         }
     }
@@ -266,8 +379,10 @@ impl App {
     fn go_right(&mut self) {
         match self.get_selected() {
             Some(selected) => {
-                let selected = &selected.path;
-                if selected.is_dir() {
+                let selected = selected.path.to_path_buf();
+                // archives get browsed like directories
+                let archive = vfs::is_archive(&selected);
+                if archive || vfs::is_dir(&selected) {
                     self.pwd = selected.to_path_buf();
                     // what a fucked up fix
                     self.left_column = mem::replace(
@@ -280,42 +395,17 @@ impl App {
                             },
                         ),
                     );
-                    self.refresh_right_column();
-                } else if selected.is_file() {
-                    // i should probably use kind
-                    match FileFormat::from_file(selected).unwrap().kind() {
-                        // TODO fix these, read the programs from a config file
-                        Kind::Book | Kind::Document => {
-                            Command::new("zathura")
-                                .arg(selected.as_path())
-                                .stderr(Stdio::null())
-                                .spawn()
-                                .expect("failed to exec");
-                        }
-                        Kind::Image => {
-                            Command::new("sxiv")
-                                .arg(selected.as_path())
-                                .stderr(Stdio::null())
-                                .spawn()
-                                .expect("failed to exec");
-                        }
-                        Kind::Video => {
-                            Command::new("vlc")
-                                .arg(selected.as_path())
-                                .stderr(Stdio::null())
-                                .spawn()
-                                .expect("failed to exec");
-                        }
-                        Kind::Text | Kind::Application => {
-                            self.set_message("opening these messes up the terminal for now")
-                            // Command::new("/usr/bin/nvim")
-                            //     .arg(selected.as_path())
-                            //     .stderr(Stdio::null())
-                            //     .spawn()
-                            //     .expect("failed to exec");
-                        }
-                        _ => self.set_message("yeah i cant open this so far"),
+                    // the listing wasnt there yet, or the right column had the
+                    // archive preview, no waiting for it now
+                    if self.loader.loading() || archive {
+                        self.middle_column.state = ListState::default();
+                        self.refresh_middle_column();
+                    } else {
+                        self.narrow(self.get_selected().map(|s| s.path.to_path_buf()));
                     }
+                    self.refresh_right_column();
+                } else {
+                    self.open(&selected);
                 }
                 self.set_metadata()
             }
@@ -323,33 +413,67 @@ impl App {
         }
     }
 
+    fn open(&mut self, selected: &Path) {
+        // things in archives have to come out first
+        let selected = match vfs::split(selected) {
+            Some(_) => match vfs::materialize(selected) {
+                Ok(path) => path,
+                Err(e) => return self.set_message(format!("could not extract it: {e}")),
+            },
+            None => selected.to_path_buf(),
+        };
+        if !selected.is_file() {
+            return;
+        }
+        let format = FileFormat::from_file(&selected).unwrap_or_default();
+        let editor = self.config.openers.editor();
+        let candidates = self.config.openers.candidates(&selected, &format, &editor);
+        if candidates.is_empty() {
+            self.set_message("yeah i cant open this so far")
+        } else {
+            match opener::open(&candidates, &selected) {
+                Ok(Opened::Background(program)) => {
+                    self.set_message(format!("opened with {program}"))
+                }
+                Ok(Opened::Foreground(mut command)) => {
+                    if self.pwd.is_dir() {
+                        command.current_dir(&self.pwd);
+                    }
+                    self.foreground = Some(command);
+                }
+                Err(message) => self.set_message(message),
+            }
+        }
+    }
+
     fn go_left(&mut self) {
         // we have to somehow select the parent when going left
-        match self.pwd.parent() {
-            Some(parent) => {
-                let parent_index: Option<usize> =
-                    get_item_index(&self.pwd, &self.left_column.items);
-                // again, i do not like the couple next lines.
-                self.right_column = mem::replace(
-                    &mut self.middle_column,
-                    mem::replace(
-                        &mut self.left_column,
-                        StatefulList {
-                            items: vec![],
-                            state: ListState::default(),
-                        },
-                    ),
-                );
-                self.middle_column.state.select(parent_index);
-                self.pwd = parent.to_path_buf();
-                match self.pwd.parent() {
-                    Some(parent) => self.left_column.items = self.ls(parent),
-                    None => self.left_column.items = vec![],
-                }
-                self.set_metadata();
-                self.set_message("");
+        if let Some(parent) = self.pwd.parent() {
+            let parent_index: Option<usize> = get_item_index(&self.pwd, &self.left_column.items);
+            // again, i do not like the couple next lines.
+            self.right_column = mem::replace(
+                &mut self.middle_column,
+                mem::replace(
+                    &mut self.left_column,
+                    StatefulList {
+                        items: vec![],
+                        state: ListState::default(),
+                    },
+                ),
+            );
+            self.middle_column.state.select(parent_index);
+            // the right column is the old pwd now, nothing to wait for
+            self.loader.cancel();
+            self.summary = String::new();
+            let old = self.pwd.to_path_buf();
+            self.pwd = parent.to_path_buf();
+            self.narrow(Some(old));
+            match self.pwd.parent() {
+                Some(parent) => self.left_column.items = self.ls(parent),
+                None => self.left_column.items = vec![],
             }
-            None => {}
+            self.set_metadata();
+            self.set_message("");
         };
     }
 
@@ -368,35 +492,103 @@ impl App {
     }
 
     fn refresh_right_column(&mut self) {
+        self.scroll = 0;
+        self.seek = None;
+        if self.mark.as_ref().map(|(path, ..)| path) != self.get_selected().map(|s| &s.path) {
+            self.mark = None;
+        }
         match self.get_selected() {
             Some(selected) => {
-                let selected_path = &selected.path;
-                let path = selected_path.as_path();
-                if selected_path.is_dir() {
-                    self.right_column.items = self.ls(&path);
-                    if self.right_column.items.len() > 0 {
-                        self.right_column.state.select(Some(0));
-                    }
-                } else if selected_path.is_file() {
-                    match FileFormat::from_file(selected_path).unwrap().kind() {
-                        Kind::Text => {
-                            if selected_path.metadata().unwrap().size().le(&200000) {
-                                let preview = "".to_string();
-                                let preview = fs::read_to_string(selected_path).unwrap_or("problem reading file".to_string());
-                                self.get_mut_selected().unwrap().set_preview(preview);
-                            } else {
-                                self.get_mut_selected().unwrap().set_preview("File too big for preview".to_string());
-                            }
-                        }
-                        _ => {}
-                    }
-                    self.right_column.items = vec![];
-                } else {
-                    // just cuz it probably needs to be handled later
-                    self.right_column.items = vec![];
+                let path = selected.path.to_path_buf();
+                // ui shows a placeholder until tick gets the result otherwise
+                if let Some(preview) = self.loader.request(&path, self.hidden, self.orderby) {
+                    self.show_preview(preview);
+                }
+            }
+            None => self.loader.cancel(),
+        }
+    }
+
+    // grep hits scroll the preview to the line, with a bit of what comes
+    // before it. the preview only has the start of the file though, a hit
+    // further down gets the end of it and a word in the footer instead of an
+    // empty pane
+    fn seek_mark(&mut self) {
+        let height = match self.get_selected().and_then(|s| s.preview.as_ref()) {
+            Some(preview) => preview.height(),
+            // tick comes back here when its loaded
+            None => return,
+        };
+        let line = match self.seek.take() {
+            Some(line) => line,
+            None => return,
+        };
+        self.scroll = line.min(height).saturating_sub(4) as u16;
+        if line > height {
+            self.set_message(format!(
+                "line {line} is past the first {height} lines the preview shows"
+            ));
+        }
+    }
+
+    // moves the preview by that many lines, it stops at the last one
+    fn scroll_preview(&mut self, lines: i32) {
+        let height = match self.get_selected().and_then(|s| s.preview.as_ref()) {
+            Some(preview) => preview.height() as i32,
+            None => return,
+        };
+        self.scroll = (self.scroll as i32 + lines).clamp(0, (height - 1).max(0)) as u16;
+    }
+
+    // moves some of the middle column over to the parent (0) or the preview
+    // (2) column, in percent of the width. none of them go away, hiding them
+    // is what panes are for
+    fn resize(&mut self, column: usize, percent: i32) {
+        let total: u32 = self.columns.iter().map(|&n| n as u32).sum();
+        let mut columns = self.columns.map(|n| (n as u32 * 100 / total) as i32);
+        columns[1] = 100 - columns[0] - columns[2];
+        // at least 5 for the side ones and 10 for the middle, unless they
+        // already started out smaller
+        let percent = percent.clamp((5 - columns[column]).min(0), (columns[1] - 10).max(0));
+        columns[column] += percent;
+        columns[1] -= percent;
+        self.columns = columns.map(|n| n as u16);
+        self.show_columns();
+    }
+
+    fn show_columns(&mut self) {
+        let [parent, middle, preview] = self.columns;
+        let hidden = match self.panes {
+            Panes::All => "",
+            Panes::NoParent => ", no parent",
+            Panes::NoPreview => ", no preview",
+            Panes::Single => ", only the middle one",
+        };
+        self.set_message(format!("columns {parent}:{middle}:{preview}{hidden}"));
+    }
+
+    fn show_preview(&mut self, preview: Preview) {
+        match preview {
+            Preview::Listing(mut items, summary) => {
+                for item in &mut items {
+                    item.tagged = self.config.tags.contains(&item.path);
                 }
+                self.right_column.state = ListState::default();
+                if !items.is_empty() {
+                    self.right_column.state.select(Some(0));
+                }
+                self.right_column.items = items;
+                self.summary = summary;
+            }
+            Preview::Text(preview) => {
+                // the column is a listing only for directories
+                self.right_column.items = vec![];
+                self.summary = String::new();
+                if let (Some(preview), Some(selected)) = (preview, self.get_mut_selected()) {
+                    selected.set_preview(preview);
+                }
+                self.seek_mark();
             }
-            None => {}
         }
     }
 
@@ -408,10 +600,64 @@ impl App {
     }
 
     fn refresh_middle_column(&mut self) {
+        let selected = self.get_selected().map(|s| s.path.to_path_buf());
         self.middle_column.items = self.ls(&self.pwd);
-        if self.middle_column.state.selected().is_none() && self.middle_column.items.len() > 0 {
+        self.narrow(selected);
+        if self.middle_column.state.selected().is_none() && !self.middle_column.items.is_empty() {
             self.middle_column.state.select(Some(0))
         }
+        self.rerank();
+    }
+
+    // the filter being typed, or else the one set for pwd
+    fn filter(&self) -> Option<Matcher> {
+        if let InputMode::Input(input) = &self.input_mode {
+            if let Some(Ok(matcher)) = input.strip_prefix(":filter ").map(Matcher::parse) {
+                return Some(matcher);
+            }
+        }
+        match &self.filter {
+            Some((dir, _, matcher)) if *dir == self.pwd => Some(matcher.clone()),
+            _ => None,
+        }
+    }
+
+    // leaves out of the middle column what the filter doesnt match. selected
+    // stays selected if it made it through
+    fn narrow(&mut self, selected: Option<PathBuf>) {
+        let matcher = match self.filter() {
+            Some(matcher) => matcher,
+            None => return,
+        };
+        self.middle_column
+            .items
+            .retain(|item| matcher.is_match(Path::new(item.path.file_name().unwrap_or_default())));
+        let items = &self.middle_column.items;
+        let index = match selected.and_then(|s| get_item_index(&s, items)) {
+            Some(index) => Some(index),
+            None if items.is_empty() => None,
+            None => Some(0),
+        };
+        self.middle_column.state.select(index);
+    }
+
+    // an empty pattern clears it
+    fn set_filter(&mut self, pattern: &str) {
+        if pattern.is_empty() {
+            self.filter = None;
+            self.set_message("filter cleared");
+        } else {
+            match Matcher::parse(pattern) {
+                Ok(matcher) => {
+                    self.filter = Some((self.pwd.to_path_buf(), pattern.to_string(), matcher));
+                    self.set_message("");
+                }
+                Err(e) => self.set_message(format!("bad pattern: {e}")),
+            }
+        }
+        self.refresh_middle_column();
+        self.refresh_right_column();
+        self.set_metadata();
     }
 
     fn refresh_all(&mut self) {
@@ -422,31 +668,33 @@ impl App {
 
     fn toggle_hidden_files(&mut self) {
         self.hidden = !self.hidden;
+        // remembered for next time
+        self.config.show_hidden = !self.hidden;
         self.refresh_all();
         self.set_metadata();
     }
 
-    fn get_selected(&self) -> Option<&Item<PathBuf, String>> {
+    fn get_selected(&self) -> Option<&Item<PathBuf, Text<'static>>> {
         self.middle_column
             .items
             .get(self.middle_column.state.selected().unwrap_or(0))
     }
 
-    fn get_mut_selected(&mut self) -> Option<&mut Item<PathBuf, String>> {
+    fn get_mut_selected(&mut self) -> Option<&mut Item<PathBuf, Text<'static>>> {
         self.middle_column
             .items
             .get_mut(self.middle_column.state.selected().unwrap_or(0))
     }
 
-    fn ls(&self, pwd: &Path) -> Vec<Item<PathBuf, String>> {
+    fn ls(&self, pwd: &Path) -> Vec<Item<PathBuf, Text<'static>>> {
         ls(pwd, self.hidden, &self.orderby, &self.config.tags)
     }
 
     fn set_metadata(&mut self) {
         let size = match self.get_selected() {
-            Some(selected) => match selected.path.metadata() {
-                Ok(metadata) => format_size(metadata.size(), DECIMAL),
-                Err(_) => String::new(),
+            Some(selected) => match vfs::len(&selected.path) {
+                Some(len) => format_size(len, DECIMAL),
+                None => String::new(),
             },
             None => String::new(),
         };
@@ -458,13 +706,6 @@ impl App {
             }
             None => String::new(),
         };
-        let (created, modified) = match self.get_selected() {
-            Some(selected) => match selected.path.metadata() {
-                Ok(metadata) => (format!("{:?}", metadata.created()), format!("{:?}", metadata.modified())),
-                Err(_) => (String::new(),String::new()),
-            }
-            None => (String::new(),String::new()),
-        };
         self.metadata = format!("{size}  {index}")
     }
 
@@ -472,8 +713,10 @@ impl App {
         self.message = message.as_ref().to_string()
     }
 
+    // runs whatever was typed in input mode. the mode goes back to normal
+    // unless the command asks for something else
     fn execute(&mut self) {
-        let command = self.input_mode.get_str();
+        let command = mem::replace(&mut self.input_mode, InputMode::Normal).get_str();
         match command.split_once(' ') {
             Some(command) => match command.0 {
                 // then it has two words as expected
@@ -483,8 +726,12 @@ impl App {
                     if src.eq(&dst) {
                         self.set_message("nothing to do")
                     } else {
-                        match rename(src, dst) {
+                        match rename(src, &dst) {
                             Ok(_) => {
+                                self.journal.record(vec![Op::Rename {
+                                    from: src.to_path_buf(),
+                                    to: dst,
+                                }]);
                                 self.set_message("renamed file");
                                 self.refresh_middle_column();
                             }
@@ -500,6 +747,10 @@ impl App {
                     if !Path::exists(&dst) {
                         match File::create(&dst) {
                             Ok(_) => {
+                                self.journal.record(vec![Op::Create {
+                                    path: dst.to_path_buf(),
+                                    dir: false,
+                                }]);
                                 self.set_message("file created");
                                 self.refresh_middle_column();
                                 let index = get_item_index(&dst, &self.middle_column.items);
@@ -514,8 +765,12 @@ impl App {
                 ":mkdir" => {
                     let dst = PathBuf::new().join(&self.pwd).join(command.1);
                     if !Path::exists(&dst) {
-                        match create_dir(dst) {
+                        match create_dir(&dst) {
                             Ok(_) => {
+                                self.journal.record(vec![Op::Create {
+                                    path: dst,
+                                    dir: true,
+                                }]);
                                 self.set_message("directory created");
                                 self.refresh_middle_column();
                             }
@@ -525,15 +780,25 @@ impl App {
                         self.set_message("path already exists")
                     }
                 }
-                ":find" => {
-                    match self.inc_find() {
-                        Some(_) => {}
-                        None => self.middle_column.state.select(Some(0)),
-                    };
-                    self.set_message("");
-                    self.refresh_right_column();
-                    self.go_right()
-                }
+                ":trash-restore" => self.trash_restore(Some(command.1)),
+                ":extract" => self.extract(Some(command.1)),
+                ":search" => self.search(command.1, false),
+                ":grep" => self.search(command.1, true),
+                ":filter" => self.set_filter(command.1),
+                ":compress" => self.compress(command.1),
+                ":paste" => match Conflict::parse(command.1) {
+                    Some(policy) => self.paste(Some(policy)),
+                    None => self.set_message("paste what? overwrite, skip, rename or ask"),
+                },
+                // whatever is highlighted in the overlay, it was ranked while
+                // typing
+                ":find" => match self.found() {
+                    Some(_) => {
+                        self.set_message("");
+                        self.go_right()
+                    }
+                    None => self.set_message("nothing like that in here"),
+                },
                 _ => {
                     // make this into some easter egg, randomize statements and throw
                     // them in for a pinch of fun
@@ -545,7 +810,13 @@ impl App {
             None => {
                 // then it has only one word
                 if command.starts_with('/') {
-                    match self.inc_search() {
+                    match command.strip_prefix('/') {
+                        Some(pattern) if !pattern.is_empty() => {
+                            self.last_search = Some(pattern.to_string())
+                        }
+                        _ => {}
+                    }
+                    match self.inc_search(&command) {
                         Some(_) => {}
                         None => self.middle_column.state.select(Some(0)),
                     };
@@ -553,6 +824,21 @@ impl App {
                     self.refresh_right_column()
                 } else {
                     match command.as_str() {
+                        ":bulkrename" => self.bulk_rename(),
+                        ":paste" => self.paste(None),
+                        ":extract" => self.extract(None),
+                        ":search" | ":grep" => self.open_search(),
+                        ":filter" => self.set_filter(""),
+                        ":compress" => self.set_message(
+                            "compress into what? a name ending in .tar.gz, .tar.xz, .tar or .zip",
+                        ),
+                        ":jobs" => self.open_jobs(),
+                        ":trash-list" => self.open_trash(),
+                        ":trash-restore" => self.trash_restore(None),
+                        ":trash-empty" => {
+                            self.input_mode = InputMode::Confirmation(Confirm::EmptyTrash, 'y');
+                            self.set_message("permanently delete everything in the trash? [y/n]")
+                        }
                         ":q" | ":quit" => {
                             // implement quitting.. lol
                             self.set_message(
@@ -566,29 +852,98 @@ impl App {
         }
     }
 
+    // called once a foreground program gave the terminal back
+    fn resume(&mut self, status: io::Result<ExitStatus>) {
+        self.refresh_all();
+        self.set_metadata();
+        match status {
+            Ok(status) if status.success() => self.set_message(""),
+            Ok(status) => self.set_message(format!("program exited with {status}")),
+            Err(e) => self.set_message(format!("could not run program: {e}")),
+        }
+        if let Some(pending) = self.bulkrename.take() {
+            match bulkrename::finish(pending) {
+                Ok(plan) if plan.renamed == 0 => self.set_message("nothing to rename"),
+                Ok(plan) => {
+                    self.set_message(format!("rename {} items? [y/n]", plan.renamed));
+                    self.input_mode = InputMode::Confirmation(Confirm::BulkRename(plan.steps), 'y');
+                }
+                Err(e) => self.set_message(format!("bulkrename: {e}")),
+            }
+        }
+    }
+
+    // opens the names of the select set, or of everything in here, in the
+    // editor. the renaming happens once it exits
+    fn bulk_rename(&mut self) {
+        let paths = match self.selection.is_empty() {
+            true => self
+                .middle_column
+                .items
+                .iter()
+                .map(|i| i.path.to_path_buf())
+                .collect(),
+            false => mem::take(&mut self.selection),
+        };
+        if paths.is_empty() {
+            self.set_message("nothing to rename");
+            return;
+        }
+        match bulkrename::start(&self.pwd, &paths) {
+            Ok(pending) => match opener::build(&self.config.openers.editor(), &pending.file) {
+                Some(command) => {
+                    self.foreground = Some(command);
+                    self.bulkrename = Some(pending);
+                }
+                None => self.set_message("could not figure out the editor command"),
+            },
+            Err(e) => self.set_message(format!("bulkrename: {e}")),
+        }
+    }
+
+    // the mode goes back to normal unless whatever got confirmed asks for
+    // something else
     fn confirm(&mut self, c: char) {
-        match &self.input_mode {
+        match mem::replace(&mut self.input_mode, InputMode::Normal) {
+            InputMode::Confirmation(Confirm::PasteConflict(mut resolving), _) => {
+                match resolving.answer(c) {
+                    true => self.resolve_paste(resolving),
+                    false => self.set_message("paste aborted"),
+                }
+            }
             InputMode::Confirmation(confirm, ch) => {
-                if c.eq(ch) {
+                if c.eq(&ch) {
                     match confirm {
-                        Confirm::DeleteFolder => match self.get_selected() {
-                            Some(selected) => {
-                                // delete all
-                                match remove_dir_all(selected.path.as_path()) {
-                                    Ok(_) => {
-                                        self.set_message("deleted!");
-                                        self.refresh_middle_column();
-                                        self.refresh_right_column();
-                                    }
-                                    Err(_) => self.set_message("cant delete"),
-                                };
-                            }
-                            None => self.set_message("Nothing is selected"),
-                        },
                         Confirm::DeleteSelection(selection) => {
                             // have to check each one if its a dir or a file
-                            self.delete_selection(&selection.clone())
+                            self.delete_selection(&selection)
+                        }
+                        Confirm::BulkRename(steps) => {
+                            let (done, error) = bulkrename::run(steps);
+                            let ops = done
+                                .into_iter()
+                                .map(|step| Op::Rename {
+                                    from: step.from,
+                                    to: step.to,
+                                })
+                                .collect();
+                            self.journal.record(ops);
+                            self.refresh_all();
+                            match error {
+                                Some(e) => self.set_message(format!(
+                                    "stopped renaming: {e}, u to undo the rest"
+                                )),
+                                None => self.set_message("renamed, u to undo"),
+                            }
                         }
+                        // handled above
+                        Confirm::PasteConflict(_) => {}
+                        Confirm::EmptyTrash => match trash::empty() {
+                            Ok(count) => {
+                                self.set_message(format!("emptied {count} items from the trash"))
+                            }
+                            Err(e) => self.set_message(format!("could not empty the trash: {e}")),
+                        },
                     }
                 } else {
                     self.set_message("aborted")
@@ -598,314 +953,731 @@ impl App {
         }
     }
 
-    // a good thing to do is to make a trash folder and collect stuff to delete
-    // there, then just before the app closes we can issue a delete...
-    // although this may make exiting a bit slower i recon... anyway
-    fn delete_file(&mut self) {
+    fn trash_file(&mut self) {
         // todo after deleting, select something else if dir isnt empty
         match self.get_selected() {
             Some(selected) => {
-                let selected = &selected.path;
-                let path = selected.as_path();
-                if selected.is_dir() {
-                    // check if empty
-                    match selected.read_dir().unwrap().next().is_none() {
-                        true => {
-                            match remove_dir(path) {
-                                Ok(_) => self.set_message("deleted empty dir"),
-                                Err(_) => self.set_message("wont delete"),
-                            };
-                        }
-                        false => {
-                            // this sucks less ig
-                            self.input_mode = InputMode::Confirmation(Confirm::DeleteFolder, 'y');
-                            self.set_message("are you sure you want to delete this folder and all of its contents? [y/n]")
-                        }
+                match trash::trash(&selected.path) {
+                    Ok(entry) => {
+                        self.journal.record(vec![Op::trashed(&entry)]);
+                        self.set_message("moved to trash, u to get it back")
                     }
-                } else if selected.is_file() {
-                    match remove_file(path) {
-                        Ok(_) => self.set_message("deleted file"),
-                        Err(_) => self.set_message("wont delete"),
-                    };
-                } else {
-                    self.set_message("this type of files hasn't been handled yet")
-                }
+                    Err(e) => self.set_message(format!("wont trash: {e}")),
+                };
                 self.refresh_middle_column();
+                self.refresh_right_column();
             }
             None => self.set_message("Nothing is selected"),
         }
     }
 
-    fn yank_file(&mut self, yankmode: PasteMode) {
-        match &self.input_mode {
-            InputMode::Select(selected) => {
-                for path in selected {
-                    self.yank_register.register.push(path.to_path_buf());
-                }
-                self.yank_register.mode = yankmode;
-            }
-            _ => match self.get_selected() {
-                Some(selected) => {
-                    let selected = &selected.path;
-                    self.yank_register.register.push(selected.to_path_buf());
-                    self.yank_register.mode = yankmode;
-                    self.set_message("file in register, type p to paste");
-                }
-                None => self.set_message("Nothing is selected"),
-            },
-        }
-    }
-
-    fn paste(&mut self) {
-        let len = self.yank_register.register.len();
-        let mut count = 0;
-        match self.yank_register.mode {
-            PasteMode::Move => {
-                for src in self.yank_register.register.clone() {
-                    let dst = PathBuf::new()
-                        .join(&self.pwd)
-                        .join(src.file_name().unwrap());
-                    if src.eq(&dst) {
-                        continue;
-                    }
-                    if src.is_file() {
-                        match copy(&src, &dst) {
-                            Ok(_) => {
-                                match remove_file(&src) {
-                                    Ok(_) => {
-                                        count = count + 1;
-                                        self.refresh_all();
-                                        let index = get_item_index(&dst, &self.middle_column.items);
-                                        // select the moved file
-                                        self.middle_column.state.select(index)
-                                    }
-                                    Err(_) => {
-                                        self.refresh_middle_column();
-                                    }
-                                };
-                            }
-                            Err(_) => {}
-                        }
-                    } else if src.is_dir() {
-                        match copy_dir_all(&src, &dst) {
-                            Ok(_) => {
-                                match remove_dir_all(&src) {
-                                    Ok(_) => {
-                                        count = count + 1;
-                                        self.refresh_all();
-                                        let index = get_item_index(&dst, &self.middle_column.items);
-                                        // select the moved file
-                                        self.middle_column.state.select(index)
-                                    }
-                                    Err(_) => self.refresh_middle_column(),
-                                }
-                            }
-                            Err(_) => {}
-                        }
-                    }
-                }
-                self.set_message(format!(
-                    "{count}/{len} items moved. if there are others i dunno about them."
-                ));
-            }
-            PasteMode::Copy => {
-                for src in self.yank_register.register.clone() {
-                    let dst = PathBuf::new()
-                        .join(&self.pwd)
-                        .join(src.file_name().unwrap());
-                    if src.eq(&dst) {
-                        continue;
-                    }
-                    if src.is_dir() {
-                        // fixme
-                        match copy_dir_all(&src, &dst) {
-                            Ok(_) => {
-                                count = count + 1;
-                                self.refresh_middle_column();
-                                let index = get_item_index(&dst, &self.middle_column.items);
-                                // select the pasted file
-                                self.middle_column.state.select(index)
-                            }
-                            Err(_) => {}
-                        }
-                    } else if src.is_file() {
-                        match copy(&src, &dst) {
-                            Ok(_) => {
-                                count = count + 1;
-                                self.refresh_middle_column();
-                                let index = get_item_index(&dst, &self.middle_column.items);
-                                // select the pasted file
-                                self.middle_column.state.select(index)
-                            }
-                            Err(_) => {}
-                        }
-                    }
-                }
+    // permanent deletion always asks first
+    fn delete_file(&mut self) {
+        match self.get_selected() {
+            Some(selected) => {
+                let selected = selected.path.to_path_buf();
                 self.set_message(format!(
-                    "{count}/{len} items copied. if there are others i dunno about them."
+                    "are you sure you want to permanently delete {}? [y/n]",
+                    selected.file_name().unwrap_or_default().to_string_lossy()
                 ));
+                self.input_mode =
+                    InputMode::Confirmation(Confirm::DeleteSelection(vec![selected]), 'y');
             }
+            None => self.set_message("Nothing is selected"),
         }
-        self.yank_register.register = Vec::new()
-    }
-
-    fn sort_by(&mut self, by: ListOrder) {
-        self.orderby = by;
-        self.refresh_all();
     }
 
-    fn toggle_tag_item(&mut self) {
-        match self.get_mut_selected() {
-            Some(selected) => {
-                selected.toggle_tagged();
-                let selected = selected.path.to_path_buf();
-                if !self.config.tags.contains(&selected) {
-                    self.config.tags.push(selected);
-                } else {
-                    match self.config.tags.iter().position(|p| *p == selected) {
-                        Some(pos) => {
-                            self.config.tags.remove(pos);
-                        }
-                        None => self.set_message("selected item not in the list of tagged items"),
-                    };
-                };
+    fn trash_selection(&mut self, selection: &[PathBuf]) {
+        let mut ops = vec![];
+        let mut failed = vec![];
+        for path in selection {
+            match trash::trash(path) {
+                Ok(entry) => ops.push(Op::trashed(&entry)),
+                Err(e) => failed.push(format!("{}: {e}", path.display())),
             }
-            None => self.set_message("nothing selected"),
         }
+        let trashed = ops.len();
+        self.journal.record(ops);
+        self.refresh_middle_column();
+        self.refresh_right_column();
+        self.set_message(match failed.is_empty() {
+            true => format!("trashed {trashed} items"),
+            false => format!(
+                "trashed {trashed} items out of {}, {} failed: {}",
+                selection.len(),
+                failed.len(),
+                failed.join(", ")
+            ),
+        })
     }
 
-    fn inc_search(&mut self) -> Option<usize> {
-        let pattern = self.input_mode.get_str();
-        if !pattern.starts_with('/') {
-            // ayo wtf are you thinking calling this function without the proper
-            // thing
-            return None;
+    fn open_trash(&mut self) {
+        let entries = trash::list();
+        if entries.is_empty() {
+            self.set_message("trash is empty");
+            return;
         }
-        let pattern = &pattern['/'.len_utf8()..].to_lowercase();
-        let index = self.middle_column.items.iter().position(|item| {
-            item.path
-                .file_name()
-                .unwrap()
-                .to_str()
-                .unwrap()
-                .to_lowercase()
-                .starts_with(pattern)
+        let items = entries
+            .iter()
+            .map(|entry| format!("{}  {}", entry.deleted, entry.original.display()))
+            .collect();
+        let mut state = ListState::default();
+        state.select(Some(0));
+        self.input_mode = InputMode::Panel(Panel {
+            title: format!("trash ({} items) enter to restore", entries.len()),
+            list: StatefulList { items, state },
+            kind: PanelKind::Trash(entries),
         });
-        // when canceled it doesnt select anything so...
-        self.middle_column.state.select(index);
-        self.refresh_middle_column();
-        index
+        self.set_message("");
     }
 
-    fn inc_find(&mut self) -> Option<usize> {
-        let pattern = self.input_mode.get_str();
-        if !pattern.starts_with(":find ") {
-            // ayo wtf are you thinking calling this function without the proper
-            // thing
-            return None;
+    // restores the most recently trashed item, or the most recent one that
+    // was called `name` in here (or at `name` if its a path)
+    fn trash_restore(&mut self, name: Option<&str>) {
+        let entries = trash::list();
+        let entry = entries.iter().find(|entry| match name {
+            Some(name) => {
+                entry.original == self.pwd.join(name)
+                    || entry.original.file_name().is_some_and(|n| n == name)
+            }
+            None => true,
+        });
+        match entry {
+            Some(entry) => self.restore(entry),
+            None => self.set_message("nothing like that in the trash"),
         }
-        let pattern = &pattern[":find ".len()..].to_lowercase();
-        // this mess here still needs A LOT of testing, it does not seem all that
-        // proof against infinite loops...
-        let index = self
-            .middle_column
-            .items
-            .iter()
-            .enumerate()
-            .map(|(i, item)| {
-                let mut score: Vec<usize> = Vec::new();
-                pattern.chars().for_each(|c| {
-                    let mut max = None;
-                    loop {
-                        match item
-                            .path
-                            .file_name()
-                            .unwrap()
-                            .to_str()
-                            .unwrap()
-                            .to_lowercase()
-                            .chars()
-                            .enumerate()
-                            .position(|(i, x)| x == c && (Some(i) > max))
-                        {
-                            Some(pos) => {
-                                if pos >= *score.iter().max().unwrap_or(&0) && !score.contains(&pos)
-                                {
-                                    score.push(pos);
-                                    break;
-                                } else {
-                                    max = Some(pos);
-                                }
-                            }
-                            None => break,
-                        }
-                    }
-                });
-                (i, score)
-            })
-            .filter(|(_, s)| s.iter().count() == pattern.len())
-            .min_by(|x, y| x.1.cmp(&y.1))
-            .map(|x| x.0);
-
-        self.middle_column.state.select(index);
-        self.refresh_middle_column();
-        index
     }
 
-    fn toggle_select(&mut self) {
-        match self.get_selected() {
-            Some(_selected) => {
-                // shit im gonna have to color these somehow
-                // ok i guess i wont color them ill just add some padding or something
+    fn restore(&mut self, entry: &TrashEntry) {
+        match trash::restore(entry) {
+            Ok(_) => {
+                self.journal.record(vec![Op::restored(entry)]);
+                self.refresh_all();
+                let index = get_item_index(&entry.original, &self.middle_column.items);
+                if index.is_some() {
+                    self.middle_column.state.select(index);
+                    self.refresh_right_column();
+                }
+                self.set_message(format!("restored {}", entry.original.display()))
             }
-            None => {}
+            Err(e) => self.set_message(format!("could not restore: {e}")),
         }
     }
 
-    fn delete_selection(&mut self, selection: &Vec<PathBuf>) {
-        let mut deleted = 0;
-        let len = selection.len();
-        for path in selection.to_vec() {
-            if path.is_dir() {
-                match remove_dir_all(path) {
-                    Ok(_) => {
-                        deleted += 1;
-                        self.refresh_middle_column();
+    fn undo(&mut self, redo: bool) {
+        let walked = match redo {
+            true => self.journal.redo(),
+            false => self.journal.undo(),
+        };
+        match walked {
+            Some(walked) => {
+                self.refresh_all();
+                if let Some(path) = walked.path {
+                    let index = get_item_index(&path, &self.middle_column.items);
+                    if index.is_some() {
+                        self.middle_column.state.select(index);
                         self.refresh_right_column();
                     }
-                    Err(_) => {}
                 }
-            } else if path.is_file() {
-                match remove_file(path) {
-                    Ok(_) => {
-                        deleted += 1;
-                        self.refresh_middle_column();
-                        self.refresh_right_column();
-                    }
-                    Err(_) => {}
+                self.set_metadata();
+                let action = if redo { "redid" } else { "undid" };
+                match walked.failed.first() {
+                    Some(failed) => self.set_message(format!(
+                        "{action} {} ops, {} failed: {failed}",
+                        walked.done,
+                        walked.failed.len()
+                    )),
+                    None => self.set_message(format!("{action} {} ops", walked.done)),
                 }
             }
+            None if redo => self.set_message("nothing to redo"),
+            None => self.set_message("nothing to undo"),
+        }
+    }
+
+    // enter on whatever is highlighted in the open panel
+    fn panel_enter(&mut self) {
+        match mem::replace(&mut self.input_mode, InputMode::Normal) {
+            InputMode::Panel(panel) => {
+                let index = panel.list.state.selected().unwrap_or(0);
+                match panel.kind {
+                    PanelKind::Trash(entries) => {
+                        if let Some(entry) = entries.get(index) {
+                            self.restore(entry)
+                        }
+                    }
+                    // enter just closes this one
+                    PanelKind::Jobs => {}
+                    PanelKind::Search => {
+                        let search = match &self.search {
+                            Some(search) => search,
+                            None => return,
+                        };
+                        let (path, line) = match search.hits.get(index) {
+                            Some(hit) => (hit.path.to_path_buf(), hit.line),
+                            None => return,
+                        };
+                        let regex = search.regex.clone();
+                        self.jump(&path);
+                        if let (Some(line), Some(regex)) = (line, regex) {
+                            self.mark = Some((path, line, regex));
+                            self.seek = Some(line);
+                            self.seek_mark();
+                        }
+                    }
+                }
+            }
+            mode => self.input_mode = mode,
+        }
+    }
+
+    // makes the directory of path the pwd, with path selected
+    fn jump(&mut self, path: &Path) {
+        let parent = match path.parent() {
+            Some(parent) => parent,
+            None => return,
+        };
+        self.pwd = parent.to_path_buf();
+        self.refresh_left_column();
+        let index = get_item_index(&self.pwd, &self.left_column.items);
+        self.left_column.state.select(index);
+        self.middle_column.state = ListState::default();
+        self.refresh_middle_column();
+        // the filter would hide what was jumped to
+        let hidden = get_item_index(path, &self.middle_column.items).is_none();
+        if hidden && matches!(&self.filter, Some((dir, _, _)) if *dir == self.pwd) {
+            self.filter = None;
+            self.refresh_middle_column();
+        }
+        if let Some(index) = get_item_index(path, &self.middle_column.items) {
+            self.middle_column.state.select(Some(index));
+        }
+        self.refresh_right_column();
+        self.set_metadata();
+        self.set_message("");
+    }
+
+    fn yank_file(&mut self, yankmode: PasteMode) {
+        match &self.input_mode {
+            InputMode::Select(selected) => {
+                for path in selected {
+                    self.yank_register.register.push(path.to_path_buf());
+                }
+                self.yank_register.mode = yankmode;
+            }
+            _ => match self.get_selected() {
+                Some(selected) => {
+                    let selected = &selected.path;
+                    self.yank_register.register.push(selected.to_path_buf());
+                    self.yank_register.mode = yankmode;
+                    self.set_message("file in register, type p to paste");
+                }
+                None => self.set_message("Nothing is selected"),
+            },
+        }
+    }
+
+    // the actual copying happens in the background, see tick. conflicts with
+    // existing names are dealt with first, by policy or by asking
+    fn paste(&mut self, policy: Option<Conflict>) {
+        if vfs::split(&self.pwd).is_some() || vfs::is_archive(&self.pwd) {
+            self.set_message("archives are read only, paste somewhere else");
+            return;
+        }
+        if matches!(self.yank_register.mode, PasteMode::Move)
+            && self
+                .yank_register
+                .register
+                .iter()
+                .any(|p| vfs::split(p).is_some())
+        {
+            self.set_message("things cant be moved out of an archive, yank them instead");
+            return;
+        }
+        let items: Vec<(PathBuf, PathBuf)> = mem::take(&mut self.yank_register.register)
+            .into_iter()
+            .filter_map(|src| {
+                let dst = self.pwd.join(src.file_name()?);
+                (src != dst).then_some((src, dst))
+            })
+            .collect();
+        if items.is_empty() {
+            self.set_message("nothing to paste");
+            return;
+        }
+        let policy = policy.unwrap_or(self.config.paste.conflict);
+        let resolving = Resolving::new(
+            self.yank_register.mode,
+            items,
+            policy,
+            self.config.paste.rename,
+        );
+        self.resolve_paste(resolving);
+    }
+
+    fn resolve_paste(&mut self, mut resolving: Resolving) {
+        if !resolving.advance() {
+            self.set_message(resolving.question());
+            self.input_mode = InputMode::Confirmation(Confirm::PasteConflict(resolving), ' ');
+            return;
+        }
+        // whatever gets overwritten goes to the trash first, the job records
+        // that along with the paste so one u takes back both
+        let mut trashed = vec![];
+        for path in &resolving.replace {
+            match trash::trash(path) {
+                Ok(entry) => trashed.push(Op::trashed(&entry)),
+                Err(e) => {
+                    self.journal.record(trashed);
+                    self.refresh_all();
+                    self.set_message(format!("could not replace {}: {e}", path.display()));
+                    return;
+                }
+            }
+        }
+        let mode = resolving.mode;
+        let items = resolving.resolved;
+        let skipped = match resolving.skipped {
+            0 => String::new(),
+            n => format!(", skipped {n}"),
+        };
+        if items.is_empty() {
+            self.journal.record(trashed);
+            self.set_message(format!("nothing to paste{skipped}"));
+            return;
+        }
+        let label = format!(
+            "{} {} items",
+            match mode {
+                PasteMode::Move => "moving",
+                PasteMode::Copy => "copying",
+            },
+            items.len()
+        );
+        self.set_message(format!(
+            "{label} in the background{skipped}, :jobs to see how it goes"
+        ));
+        self.jobs.submit(
+            label,
+            Task::Paste {
+                mode,
+                items,
+                trashed,
+            },
+        );
+    }
+
+    // the select set if there is one, the highlighted item otherwise
+    fn targets(&mut self) -> Vec<PathBuf> {
+        match self.selection.is_empty() {
+            true => self
+                .get_selected()
+                .map(|selected| selected.path.to_path_buf())
+                .into_iter()
+                .collect(),
+            false => mem::take(&mut self.selection),
+        }
+    }
+
+    // each archive goes in a directory named after it, or everything goes in
+    // dest
+    fn extract(&mut self, dest: Option<&str>) {
+        if vfs::split(&self.pwd).is_some() || vfs::is_archive(&self.pwd) {
+            self.set_message("archives are read only, extract somewhere else");
+            return;
+        }
+        let archives = self.targets();
+        if archives.is_empty() {
+            self.set_message("nothing to extract");
+            return;
+        }
+        if let Some(archive) = archives.iter().find(|a| !vfs::is_archive(a)) {
+            self.set_message(format!(
+                "{} is not an archive lga can extract",
+                archive.file_name().unwrap_or_default().to_string_lossy()
+            ));
+            return;
+        }
+        let mut taken = HashSet::new();
+        let items: Vec<(PathBuf, PathBuf)> = archives
+            .into_iter()
+            .map(|archive| {
+                let dst = match dest {
+                    Some(dest) => self.pwd.join(dest),
+                    None => {
+                        let stem = compress::stem(&archive);
+                        match stem.symlink_metadata().is_ok() || taken.contains(&stem) {
+                            true => free_name(&stem, self.config.paste.rename, &taken),
+                            false => stem,
+                        }
+                    }
+                };
+                taken.insert(dst.to_path_buf());
+                (archive, dst)
+            })
+            .collect();
+        let label = match &items[..] {
+            [(archive, _)] => format!(
+                "extracting {}",
+                archive.file_name().unwrap_or_default().to_string_lossy()
+            ),
+            items => format!("extracting {} archives", items.len()),
+        };
+        self.set_message(format!(
+            "{label} in the background, :jobs to see how it goes"
+        ));
+        self.jobs.submit(label, Task::Extract { items });
+    }
+
+    fn compress(&mut self, name: &str) {
+        if vfs::split(&self.pwd).is_some() || vfs::is_archive(&self.pwd) {
+            self.set_message("archives are read only, compress somewhere else");
+            return;
+        }
+        let archive = self.pwd.join(name);
+        if compress::kind(&archive).is_none() {
+            self.set_message("compress into what? a name ending in .tar.gz, .tar.xz, .tar or .zip");
+            return;
+        }
+        if archive.symlink_metadata().is_ok() {
+            self.set_message("path already exists");
+            return;
+        }
+        let sources = self.targets();
+        if sources.is_empty() {
+            self.set_message("nothing to compress");
+            return;
+        }
+        let label = format!("compressing {} items into {name}", sources.len());
+        self.set_message(format!(
+            "{label} in the background, :jobs to see how it goes"
+        ));
+        self.jobs.submit(label, Task::Compress { sources, archive });
+    }
+
+    // looks for pattern in names under pwd, or in whats in the files with
+    // contents, results show up as they come
+    fn search(&mut self, pattern: &str, contents: bool) {
+        if !self.pwd.is_dir() {
+            self.set_message("cant search in archives");
+            return;
+        }
+        let search = match contents {
+            true => Search::contents(&self.pwd, pattern, self.hidden),
+            false => Search::names(&self.pwd, pattern, self.hidden),
+        };
+        match search {
+            Ok(search) => {
+                self.search = Some(search);
+                self.open_search();
+            }
+            Err(e) => self.set_message(format!("bad pattern: {e}")),
+        }
+    }
+
+    // shows the hits of the last search again
+    fn open_search(&mut self) {
+        let search = match &self.search {
+            Some(search) => search,
+            None => {
+                self.set_message("search for what?");
+                return;
+            }
+        };
+        let mut state = ListState::default();
+        state.select(Some(0));
+        self.input_mode = InputMode::Panel(Panel {
+            title: search.title(),
+            list: StatefulList {
+                items: search.lines.to_vec(),
+                state,
+            },
+            kind: PanelKind::Search,
+        });
+        self.set_message("");
+    }
+
+    // called every time around the loop, picks up whatever the jobs finished
+    fn tick(&mut self) {
+        if let Some((path, preview)) = self.loader.poll() {
+            if self
+                .get_selected()
+                .is_some_and(|selected| selected.path == path)
+            {
+                self.show_preview(preview);
+            }
+        }
+        let finished = self.jobs.poll();
+        for job in &finished {
+            self.journal
+                .record(job.trashed.iter().chain(&job.ops).cloned().collect());
+            let message = match (job.canceled, job.errors.first()) {
+                (true, _) => format!("{}: canceled after {} items", job.label, job.ops.len()),
+                (false, Some(error)) => format!(
+                    "{}: {} done, {} failed: {error}",
+                    job.label,
+                    job.ops.len(),
+                    job.errors.len()
+                ),
+                (false, None) => format!("{}: done", job.label),
+            };
+            self.set_message(message);
+        }
+        if !finished.is_empty() {
+            self.refresh_all();
+            // select the last pasted thing if its in here
+            let last = finished.iter().rev().find_map(|job| job.last.as_ref());
+            if let Some(index) =
+                last.and_then(|last| get_item_index(last, &self.middle_column.items))
+            {
+                self.middle_column.state.select(Some(index));
+                self.refresh_right_column();
+            }
+            self.set_metadata();
+        }
+        if let Some(search) = &mut self.search {
+            let new = search.poll();
+            if let InputMode::Panel(panel) = &mut self.input_mode {
+                if let PanelKind::Search = panel.kind {
+                    let lines = &search.lines[search.lines.len() - new..];
+                    panel.list.items.extend(lines.iter().cloned());
+                    panel.title = search.title();
+                }
+            }
+        }
+        if let InputMode::Panel(panel) = &mut self.input_mode {
+            if let PanelKind::Jobs = panel.kind {
+                panel.list.items = self.jobs.lines();
+                if panel.list.state.selected().unwrap_or(0) >= panel.list.items.len() {
+                    panel
+                        .list
+                        .state
+                        .select(panel.list.items.len().checked_sub(1));
+                }
+            }
+        }
+    }
+
+    fn open_jobs(&mut self) {
+        if self.jobs.is_empty() {
+            self.set_message("no jobs running");
+            return;
+        }
+        let mut state = ListState::default();
+        state.select(Some(0));
+        self.input_mode = InputMode::Panel(Panel {
+            title: format!(
+                "jobs: {} to pause/resume, {} to cancel",
+                self.keymap.keys_for(Mode::Panel, Action::PauseJob),
+                self.keymap.keys_for(Mode::Panel, Action::CancelJob)
+            ),
+            list: StatefulList {
+                items: self.jobs.lines(),
+                state,
+            },
+            kind: PanelKind::Jobs,
+        });
+        self.set_message("");
+    }
+
+    fn sort_by(&mut self, by: ListOrder) {
+        self.orderby = by;
+        self.refresh_all();
+    }
+
+    fn toggle_tag_item(&mut self) {
+        match self.get_mut_selected() {
+            Some(selected) => {
+                selected.toggle_tagged();
+                let selected = selected.path.to_path_buf();
+                if !self.config.tags.contains(&selected) {
+                    self.config.tags.push(selected);
+                } else {
+                    match self.config.tags.iter().position(|p| *p == selected) {
+                        Some(pos) => {
+                            self.config.tags.remove(pos);
+                        }
+                        None => self.set_message("selected item not in the list of tagged items"),
+                    };
+                };
+            }
+            None => self.set_message("nothing selected"),
+        }
+    }
+
+    // the items with pattern anywhere in their name
+    fn search_matches(&self, pattern: &str) -> Vec<usize> {
+        let ignore_case =
+            !self.config.search.smart_case || !pattern.chars().any(char::is_uppercase);
+        let pattern = match ignore_case {
+            true => pattern.to_lowercase(),
+            false => pattern.to_string(),
+        };
+        self.middle_column
+            .items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| {
+                let name = item.path.file_name().unwrap_or_default().to_string_lossy();
+                match ignore_case {
+                    true => name.to_lowercase().contains(&pattern),
+                    false => name.contains(&pattern),
+                }
+            })
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    // goes to the first match from the cursor on, wrapping around
+    fn inc_search(&mut self, pattern: &str) -> Option<usize> {
+        // ayo wtf are you thinking calling this function without the proper
+        // thing
+        let pattern = pattern.strip_prefix('/')?;
+        let matches = self.search_matches(pattern);
+        let cursor = self.middle_column.state.selected().unwrap_or(0);
+        let index = matches
+            .iter()
+            .find(|&&index| index >= cursor)
+            .or(matches.first())
+            .copied();
+        // when canceled it doesnt select anything so...
+        self.middle_column.state.select(index);
+        self.refresh_middle_column();
+        index
+    }
+
+    // n and N, the next or previous match of the last search
+    fn search_next(&mut self, forward: bool) {
+        let pattern = match &self.last_search {
+            Some(pattern) => pattern.to_string(),
+            None => return self.set_message("nothing was searched for yet"),
+        };
+        let matches = self.search_matches(&pattern);
+        let cursor = self.middle_column.state.selected().unwrap_or(0);
+        let (index, wrapped) = match forward {
+            true => match matches.iter().find(|&&index| index > cursor) {
+                Some(&index) => (Some(index), ""),
+                None => (matches.first().copied(), ", wrapped to the top"),
+            },
+            false => match matches.iter().rev().find(|&&index| index < cursor) {
+                Some(&index) => (Some(index), ""),
+                None => (matches.last().copied(), ", wrapped to the bottom"),
+            },
+        };
+        match index {
+            Some(index) => {
+                self.middle_column.state.select(Some(index));
+                let position = matches.iter().position(|&i| i == index).unwrap_or(0) + 1;
+                let count = matches.len();
+                self.set_message(format!("/{pattern} {position}/{count}{wrapped}"));
+                self.refresh_right_column();
+                self.set_metadata();
+            }
+            None => self.set_message(format!("no match for {pattern}")),
+        }
+    }
+
+    // ranks the names in here against the pattern, the best one gets selected
+    // and the rest show up in the overlay
+    fn inc_find(&mut self, pattern: &str) -> Option<usize> {
+        self.rank(pattern.strip_prefix(":find ")?);
+        self.finder.state = ListState::default();
+        if !self.finder.items.is_empty() {
+            self.finder.state.select(Some(0));
+        }
+        self.found()
+    }
+
+    fn rank(&mut self, pattern: &str) {
+        let names: Vec<String> = self
+            .middle_column
+            .items
+            .iter()
+            .map(|item| {
+                item.path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string()
+            })
+            .collect();
+        self.finder.items = fuzzy::rank(pattern, names.iter().map(String::as_str))
+            .into_iter()
+            .map(|m| (self.middle_column.items[m.index].path.to_path_buf(), m))
+            .collect();
+    }
+
+    // the middle column was listed again while :find is open, the candidates
+    // get ranked again and the highlighted one stays if it is still there
+    fn rerank(&mut self) {
+        let pattern = match &self.input_mode {
+            InputMode::Input(input) => match input.strip_prefix(":find ") {
+                Some(pattern) => pattern.to_string(),
+                None => return,
+            },
+            _ => return,
+        };
+        let highlighted = self
+            .finder
+            .state
+            .selected()
+            .and_then(|i| self.finder.items.get(i))
+            .map(|(path, _)| path.to_path_buf());
+        self.rank(&pattern);
+        let index = highlighted
+            .and_then(|h| self.finder.items.iter().position(|(path, _)| *path == h))
+            .or((!self.finder.items.is_empty()).then_some(0));
+        self.finder.state.select(index);
+    }
+
+    // selects the candidate highlighted in the overlay
+    fn found(&mut self) -> Option<usize> {
+        let index = self
+            .finder
+            .state
+            .selected()
+            .and_then(|i| self.finder.items.get(i))
+            .and_then(|(path, _)| get_item_index(path, &self.middle_column.items));
+        if index.is_some() {
+            self.middle_column.state.select(index);
+            self.refresh_right_column();
+            self.set_metadata();
+        }
+        index
+    }
+
+    fn delete_selection(&mut self, selection: &[PathBuf]) {
+        let mut deleted = 0;
+        let len = selection.len();
+        for path in selection {
+            let removed = match path.is_dir() {
+                true => remove_dir_all(path).is_ok(),
+                false => path.is_file() && remove_file(path).is_ok(),
+            };
+            if removed {
+                deleted += 1;
+                self.refresh_middle_column();
+                self.refresh_right_column();
+            }
         }
         self.set_message(format!("deleted {deleted} items out of {len}"))
     }
 }
 
-fn get_item_index<T>(item: &Path, items: &Vec<Item<PathBuf, T>>) -> Option<usize> {
+fn get_item_index<T>(item: &Path, items: &[Item<PathBuf, T>]) -> Option<usize> {
     items.iter().position(|i| i.path.eq(item))
 }
 
-fn ls<T: std::cmp::Ord>(
-    pwd: &Path,
-    hidden: bool,
-    order: &ListOrder,
-    tags: &Vec<PathBuf>,
-) -> Vec<Item<PathBuf, T>> {
-    let paths = fs::read_dir(pwd);
+fn ls<T>(pwd: &Path, hidden: bool, order: &ListOrder, tags: &[PathBuf]) -> Vec<Item<PathBuf, T>> {
+    let paths = match fs::read_dir(pwd) {
+        Ok(paths) => Ok(paths.flatten().map(|p| p.path()).collect()),
+        // maybe its inside an archive
+        Err(_) => vfs::read_dir(pwd),
+    };
     match paths {
         Ok(paths) => {
             let mut paths = paths
                 .into_iter()
-                .map(|p| p.unwrap().path())
                 // filter hidden files or not depending on the hidden argument
                 .filter(|p| !hidden || !p.file_name().unwrap().to_str().unwrap().starts_with("."))
                 .map(|p| {
@@ -913,7 +1685,8 @@ fn ls<T: std::cmp::Ord>(
                     // tagged paths and see if the path is .. tagged.. lol
                     // TODO
                     let tagged = tags.contains(&p);
-                    Item::new(p, tagged)
+                    let metadata = p.symlink_metadata().ok();
+                    Item::new(p, tagged, metadata)
                 })
                 .collect::<Vec<Item<PathBuf, T>>>();
 
@@ -933,40 +1706,39 @@ fn ls<T: std::cmp::Ord>(
             };
 
             match order {
-                ListOrder::Default => paths,
                 ListOrder::Name => {
-                    paths.sort();
+                    paths.sort_by(|a, b| a.path.cmp(&b.path));
                     paths
                 }
                 ListOrder::NameReverse => {
-                    paths.sort();
+                    paths.sort_by(|a, b| a.path.cmp(&b.path));
                     paths.reverse();
                     paths
                 }
                 ListOrder::Created => {
-                    paths.sort_by(|a, b| get_date_created(a).cmp(&get_date_created(b)));
+                    paths.sort_by_key(get_date_created);
                     paths
                 }
                 ListOrder::CreatedReverse => {
-                    paths.sort_by(|a, b| get_date_created(a).cmp(&get_date_created(b)));
+                    paths.sort_by_key(get_date_created);
                     paths.reverse();
                     paths
                 }
                 ListOrder::Modified => {
-                    paths.sort_by(|a, b| get_date_modified(a).cmp(&get_date_modified(b)));
+                    paths.sort_by_key(get_date_modified);
                     paths
                 }
                 ListOrder::ModifiedReverse => {
-                    paths.sort_by(|a, b| get_date_modified(a).cmp(&get_date_modified(b)));
+                    paths.sort_by_key(get_date_modified);
                     paths.reverse();
                     paths
                 }
                 ListOrder::DirsFirst => {
-                    paths.sort_by(|a, b| a.path.is_file().cmp(&b.path.is_file()));
+                    paths.sort_by_key(|p| !vfs::is_dir(&p.path));
                     paths
                 }
                 ListOrder::FilesFirst => {
-                    paths.sort_by(|a, b| a.path.is_dir().cmp(&b.path.is_dir()));
+                    paths.sort_by_key(|p| vfs::is_dir(&p.path));
                     paths
                 }
             }
@@ -977,24 +1749,25 @@ fn ls<T: std::cmp::Ord>(
     }
 }
 
+// copies files and whole trees with their metadata, see copy.rs
 fn copy_dir_all(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> io::Result<()> {
     if dst.as_ref().starts_with(src.as_ref()) {
-        return Err(Error::new(ErrorKind::Other, "copying src inside dst"));
-    }
-    fs::create_dir_all(&dst)?;
-    for entry in fs::read_dir(src)? {
-        let entry = entry?;
-        let ty = entry.file_type()?;
-        if ty.is_dir() {
-            copy_dir_all(entry.path(), dst.as_ref().join(entry.file_name()))?;
-        } else {
-            fs::copy(entry.path(), dst.as_ref().join(entry.file_name()))?;
-        }
+        return Err(Error::other("copying src inside dst"));
     }
-    Ok(())
+    copy::copy(src.as_ref(), dst.as_ref(), &mut |_| Ok(()))
 }
 
 fn main() -> Result<(), io::Error> {
+    // before the terminal gets taken over, so whats wrong can be read
+    let loaded = config::dir().and_then(|dir| Ok((config::load(&dir)?, dir)));
+    let ((config, note), dir) = match loaded {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("lga: {e}");
+            process::exit(1);
+        }
+    };
+    let journal = dir.join("journal.toml");
     // setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -1023,10 +1796,15 @@ fn main() -> Result<(), io::Error> {
 
     // create app and run it
     // take argument or get cwd
-    let mut app = App::new(pwd, true);
+    let mut app = App::new(pwd, config);
+    app.journal = load_path(&journal).unwrap_or_default();
+    if let Some(note) = note.filter(|_| app.message.is_empty()) {
+        app.set_message(note);
+    }
     app.middle_column.state.select(Some(0));
+    app.refresh_right_column();
     let res = run_app(&mut terminal, &mut app);
-    store("lga", Some("tags"), app.config).unwrap();
+    vfs::cleanup();
 
     // restore terminal
     disable_raw_mode()?;
@@ -1037,6 +1815,15 @@ fn main() -> Result<(), io::Error> {
     )?;
     terminal.show_cursor()?;
 
+    // saved once the terminal is back, so failing to shows up where it can
+    // be read
+    if let Err(e) = config::store(&app.config, &dir) {
+        eprintln!("lga: could not save the config: {e}");
+    }
+    if let Err(e) = store_path(&journal, &app.journal) {
+        eprintln!("lga: could not save the journal: {e}");
+    }
+
     if let Err(err) = res {
         println!("{:?}", err)
     }
@@ -1044,315 +1831,521 @@ fn main() -> Result<(), io::Error> {
     Ok(())
 }
 
+// hands the terminal over to a program like $EDITOR and takes it back once the
+// program exits
+fn suspend<B: Backend>(
+    terminal: &mut Terminal<B>,
+    mut command: Command,
+) -> io::Result<io::Result<ExitStatus>> {
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture)?;
+    terminal.show_cursor()?;
+    let status = command.status();
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)?;
+    terminal.clear()?;
+    Ok(status)
+}
+
 fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> io::Result<()> {
     loop {
+        if let Some(command) = app.foreground.take() {
+            let status = suspend(terminal, command)?;
+            app.resume(status);
+        }
+        app.tick();
         terminal.draw(|f| ui::ui(f, app))?;
 
+        // only wake up on our own while there is something to wait for
+        let searching = app.search.as_ref().is_some_and(|search| !search.done());
+        let wait = match (app.loader.loading(), app.jobs.is_empty() && !searching) {
+            (true, _) => Some(Duration::from_millis(20)),
+            (false, false) => Some(Duration::from_millis(250)),
+            (false, true) => None,
+        };
+        if let Some(wait) = wait {
+            if !event::poll(wait)? {
+                continue;
+            }
+        }
         if let Event::Key(key) = event::read()? {
-            match app.input_mode {
-                InputMode::Normal => match key.code {
-                    KeyCode::Char('q') => return Ok(()),
-                    KeyCode::Char('l') | KeyCode::Right | KeyCode::Enter => {
-                        // go right
-                        app.go_right();
-                    }
-                    KeyCode::Char('k') | KeyCode::Up => {
-                        // go up
-                        app.go_up()
-                    }
-                    KeyCode::Char('j') | KeyCode::Down => {
-                        // go down
-                        app.go_down();
-                    }
-                    KeyCode::Char('h') | KeyCode::Left => {
-                        // go left
-                        app.go_left();
-                    }
-                    KeyCode::Char('g') | KeyCode::PageUp => {
-                        // go to the beginning
-                        app.middle_column
-                            .state
-                            .select(app.middle_column.items.len().gt(&0).then_some(0));
-                        app.refresh_middle_column();
-                        app.refresh_right_column();
-                        app.set_metadata();
-                        app.set_message("");
-                    }
-                    KeyCode::Char('G') | KeyCode::PageDown => {
-                        // go to the end
-                        app.middle_column
-                            .state
-                            .select(app.middle_column.items.len().checked_sub(1));
-                        app.refresh_middle_column();
-                        app.refresh_right_column();
-                        app.set_metadata();
-                        app.set_message("");
-                    }
-                    KeyCode::Char('d') => {
-                        // implement deleting stuff
-                        app.set_message("type D to delete or d to move");
-                        app.input_mode = InputMode::Command("d".to_string());
-                    }
-                    KeyCode::Char('y') | KeyCode::Char('Y') => {
-                        // yank stuff
-                        app.set_message("type y to yank");
-                        app.input_mode = InputMode::Command("y".to_string());
-                    }
-                    KeyCode::Char('p') => {
-                        app.set_message("pasted");
-                        app.paste();
-                    }
-                    KeyCode::Char('s') => {
-                        // sort
-                        app.set_message(
-                            "sort by name [N/n], modified date [M/m], dirs first [d], files first [f]",
-                        );
-                        app.input_mode = InputMode::Command("s".to_string());
-                    }
-                    KeyCode::Char('a') => match app.get_selected() {
-                        Some(selected) => {
-                            let selected = selected.path.file_name().unwrap().to_str().unwrap();
-                            app.input_mode = InputMode::Input(format!(":rename {selected}"));
-                            app.set_message(app.input_mode.get_str());
-                        }
-                        None => {
-                            app.set_message("nothing is selected");
-                        }
-                    },
-                    KeyCode::Char(':') => {
-                        app.input_mode = InputMode::Input(":".to_string());
-                        app.set_message(app.input_mode.get_str());
-                    }
-                    KeyCode::Backspace => {
-                        app.toggle_hidden_files();
-                    }
-                    KeyCode::Char('t') => {
-                        app.toggle_tag_item();
-                    }
-                    KeyCode::Char('/') => {
-                        // implement incremental search
-                        app.input_mode = InputMode::Input("/".to_string());
-                        app.set_message(app.input_mode.get_str());
-                    }
-                    KeyCode::Char('f') => {
-                        // implement incremental search
-                        app.input_mode = InputMode::Input(":find ".to_string());
-                        app.set_message(app.input_mode.get_str());
-                    }
-                    KeyCode::Char(' ') => {
-                        // select the current thing
-                        match app.get_selected() {
-                            Some(selected) => {
-                                app.input_mode =
-                                    InputMode::Select(vec![selected.path.to_path_buf()]);
-                                app.go_down();
-                                app.set_message("Selected 1 item.")
-                            }
-                            None => app.set_message("nothing is selected"),
-                        };
-                    }
-                    KeyCode::Char('T') => {
-                        // TODO needs refinement to only include tags in cwd
-                        app.set_message(format!("Tags count: {}", app.config.tags.len()))
-                    }
-                    _ => {}
-                },
-                InputMode::Command(ref mut command) => match key.code {
-                    KeyCode::Char(c) => {
-                        command.push(c);
-                        match command.as_str() {
-                            "dD" => {
-                                app.input_mode = InputMode::Normal;
-                                app.delete_file();
-                            }
-                            "dd" => {
-                                app.input_mode = InputMode::Normal;
-                                app.yank_file(PasteMode::Move)
-                            }
-                            "yy" => {
-                                app.input_mode = InputMode::Normal;
-                                app.yank_file(PasteMode::Copy)
-                            }
-                            "sn" => {
-                                // sort by name
-                                app.input_mode = InputMode::Normal;
-                                app.sort_by(ListOrder::Name);
-                            }
-                            "sN" => {
-                                // sort by name in reverse
-                                app.input_mode = InputMode::Normal;
-                                app.sort_by(ListOrder::NameReverse);
-                            }
-                            "sc" => {
-                                // sort by created
-                                app.input_mode = InputMode::Normal;
-                                app.sort_by(ListOrder::Created);
-                            }
-                            "sC" => {
-                                // sort by created
-                                app.input_mode = InputMode::Normal;
-                                app.sort_by(ListOrder::CreatedReverse);
-                            }
-                            "sm" => {
-                                // sort by modified
-                                app.input_mode = InputMode::Normal;
-                                app.sort_by(ListOrder::Modified);
-                            }
-                            "sM" => {
-                                // sort by modified
-                                app.input_mode = InputMode::Normal;
-                                app.sort_by(ListOrder::ModifiedReverse);
-                            }
-                            "sd" => {
-                                // sort by type: dirs first
-                                app.input_mode = InputMode::Normal;
-                                app.sort_by(ListOrder::DirsFirst);
-                            }
-                            "sf" => {
-                                // sort by type: files first
-                                app.input_mode = InputMode::Normal;
-                                app.sort_by(ListOrder::FilesFirst);
-                            }
-                            _ => {
-                                app.input_mode = InputMode::Normal;
-                                app.set_message("command not found");
-                            }
-                        }
-                    }
-                    KeyCode::Esc => {
-                        app.input_mode = InputMode::Normal;
-                        app.set_message("canceled");
-                    }
-                    _ => {}
-                },
-                InputMode::Input(_) => match key.code {
-                    KeyCode::Char(c) => {
-                        app.input_mode.push_char(c);
-                        app.set_message(app.input_mode.get_str());
-                        if app.input_mode.get_str().starts_with('/') {
-                            // incrementally highlight the found thing
-                            app.inc_search();
-                        } else if app.input_mode.get_str().starts_with(":find ") {
-                            // incrementally highlight the found thing
-                            app.inc_find();
-                        }
-                    }
-                    KeyCode::Enter => {
-                        // execute the command somehow
-                        app.execute();
-                        app.input_mode = InputMode::Normal;
-                    }
-                    KeyCode::Backspace => {
-                        app.input_mode.pop_char();
-                        app.set_message(app.input_mode.get_str());
-                        // a bit of a special case here for find
-                        if app.input_mode.get_str().starts_with(":find") {
-                            app.inc_find();
-                        } else if app.input_mode.get_str().starts_with('/') {
-                            app.inc_search();
-                        }
-                    }
-                    KeyCode::Esc => {
-                        app.set_message("canceled");
-                        app.refresh_right_column();
-                        app.input_mode = InputMode::Normal;
-                    }
-                    _ => {}
-                },
-                InputMode::Confirmation(_, _) => match key.code {
-                    KeyCode::Char(c) => {
-                        app.confirm(c);
-                        app.input_mode = InputMode::Normal;
+            if press(app, key.into()) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+// feeds a key through the keymap of the mode lga is in, true when its time to
+// quit
+fn press(app: &mut App, key: Key) -> bool {
+    let mode = match app.input_mode {
+        InputMode::Normal => Mode::Normal,
+        InputMode::Select(_) => Mode::Select,
+        InputMode::Input(_) => Mode::Input,
+        InputMode::Panel(_) => Mode::Panel,
+        InputMode::Confirmation(_, _) => Mode::Confirm,
+    };
+    app.keys.push(key);
+    match app.keymap.lookup(mode, &app.keys) {
+        Lookup::Action(action) => {
+            app.keys.clear();
+            return match mode {
+                Mode::Normal => normal(app, action),
+                Mode::Select => select(app, action),
+                Mode::Input => {
+                    input(app, action);
+                    false
+                }
+                Mode::Panel => {
+                    panel(app, action);
+                    false
+                }
+                // cancel is all there is
+                Mode::Confirm => {
+                    app.set_message("aborted");
+                    app.input_mode = InputMode::Normal;
+                    false
+                }
+            };
+        }
+        // say what can come next, the input is whats shown while typing
+        Lookup::Prefix if mode != Mode::Input => {
+            let hint = app.keymap.hint(mode, &app.keys);
+            app.set_message(hint);
+        }
+        Lookup::Prefix => {}
+        Lookup::None => {
+            let keys = mem::take(&mut app.keys);
+            match mode {
+                // whatever isnt bound gets typed
+                Mode::Input => {
+                    for c in keys.iter().filter_map(Key::char) {
+                        type_char(app, c);
                     }
+                }
+                // a char is the answer, anything else means no
+                Mode::Confirm => match (keys.len(), key.char()) {
+                    (1, Some(c)) => app.confirm(c),
                     _ => {
                         app.set_message("aborted");
                         app.input_mode = InputMode::Normal;
                     }
                 },
-                InputMode::Select(ref v) => {
-                    let mut len = v.len();
-                    match key.code {
-                        KeyCode::Char('q') => return Ok(()),
-                        KeyCode::Char(' ') => {
-                            // select the current thing
-                            match app.get_selected() {
-                                Some(selected) => {
-                                    let selected = &selected.path;
-                                    if !v.contains(selected) {
-                                        app.input_mode.push_path(selected.to_path_buf());
-                                        len = len + 1;
-                                        app.go_down();
-                                    } else {
-                                        match v.iter().position(|x| x == selected) {
-                                            Some(index) => {
-                                                app.input_mode.remove_path(index);
-                                                len = len - 1;
-                                                app.go_down();
-                                            }
-                                            None => {}
-                                        }
-                                    }
-                                    // app.toggle_select();
-                                    app.set_message(format!("Selected {} items.", len));
-                                }
-                                None => app.set_message("nothing is selected"),
-                            };
-                        }
-                        KeyCode::Char('h') | KeyCode::Left => {
-                            app.go_left();
-                            app.input_mode = InputMode::Normal;
-                        }
-                        KeyCode::Char('l') | KeyCode::Right => {
-                            app.go_right();
-                            app.input_mode = InputMode::Normal;
-                        }
-                        KeyCode::Char('k') | KeyCode::Up => {
-                            // go up
-                            app.go_up();
-                            app.set_message(format!("Selected {} items.", len));
-                        }
-                        KeyCode::Char('j') | KeyCode::Down => {
-                            // go down
-                            app.go_down();
-                            app.set_message(format!("Selected {} items.", len));
-                        }
-                        KeyCode::Esc => {
-                            app.set_message("canceled");
-                            app.refresh_right_column();
-                            app.input_mode = InputMode::Normal;
-                        }
-                        KeyCode::Backspace => {
-                            app.toggle_hidden_files();
-                            app.set_message(format!("Selected {} items.", len));
-                        }
-                        KeyCode::Char(c) => match c {
-                            'd' => {
-                                app.yank_register.register = v.to_vec();
-                                app.yank_register.mode = PasteMode::Move;
-                                app.input_mode = InputMode::Normal;
-                                app.set_message("files in register, type p to paste")
-                            }
-                            'D' => {
-                                app.input_mode = InputMode::Confirmation(
-                                    Confirm::DeleteSelection(v.to_vec()),
-                                    'Y',
-                                );
-                                app.set_message(
-                                    "are you sure you want to delete all selected items? [Y/n]",
-                                )
-                            }
-                            'y' => {
-                                app.yank_register.register = v.to_vec();
-                                app.yank_register.mode = PasteMode::Copy;
-                                app.input_mode = InputMode::Normal;
-                                app.set_message("files in register, type p to paste")
-                            }
-                            _ => {}
-                        },
-                        _ => {}
+                _ if keys.len() == 1 => {}
+                _ if key.code == KeyCode::Esc => app.set_message("canceled"),
+                _ => app.set_message("command not found"),
+            }
+        }
+    }
+    false
+}
+
+fn normal(app: &mut App, action: Action) -> bool {
+    match action {
+        Action::Quit => return true,
+        Action::GoRight => app.go_right(),
+        Action::GoUp => app.go_up(),
+        Action::GoDown => app.go_down(),
+        Action::GoLeft => app.go_left(),
+        Action::GoTop => {
+            // go to the beginning
+            app.middle_column
+                .state
+                .select(app.middle_column.items.len().gt(&0).then_some(0));
+            app.refresh_middle_column();
+            app.refresh_right_column();
+            app.set_metadata();
+            app.set_message("");
+        }
+        Action::GoBottom => {
+            // go to the end
+            app.middle_column
+                .state
+                .select(app.middle_column.items.len().checked_sub(1));
+            app.refresh_middle_column();
+            app.refresh_right_column();
+            app.set_metadata();
+            app.set_message("");
+        }
+        // the preview scrolls on its own, half a page or a line at a time
+        Action::ScrollHalfDown => app.scroll_preview((app.preview_height / 2).max(1) as i32),
+        Action::ScrollHalfUp => app.scroll_preview(-((app.preview_height / 2).max(1) as i32)),
+        Action::ScrollDown => app.scroll_preview(1),
+        Action::ScrollUp => app.scroll_preview(-1),
+        Action::ToggleWrap => {
+            app.wrap = !app.wrap;
+            app.set_message(match app.wrap {
+                true => "wrapping long lines",
+                false => "not wrapping long lines",
+            });
+        }
+        Action::ToggleLineNumbers => {
+            app.line_numbers = !app.line_numbers;
+            app.set_message("");
+        }
+        Action::ToggleParent => {
+            app.panes = app.panes.toggle_parent();
+            app.show_columns();
+        }
+        Action::TogglePreview => {
+            app.panes = app.panes.toggle_preview();
+            app.show_columns();
+        }
+        // back to how the config has it, or everything when that is single too
+        Action::ToggleSingle => {
+            app.panes = match (app.panes, app.config.panes) {
+                (Panes::Single, Panes::Single) => Panes::All,
+                (Panes::Single, panes) => panes,
+                _ => Panes::Single,
+            };
+            app.show_columns();
+        }
+        Action::WidenParent => app.resize(0, 5),
+        Action::NarrowParent => app.resize(0, -5),
+        Action::WidenPreview => app.resize(2, 5),
+        Action::NarrowPreview => app.resize(2, -5),
+        Action::ToggleDetails => {
+            app.details = !app.details;
+            app.set_message("");
+        }
+        Action::ResetColumns => {
+            app.columns = app.config.columns;
+            app.panes = app.config.panes;
+            app.show_columns();
+        }
+        Action::Trash => app.trash_file(),
+        Action::Delete => app.delete_file(),
+        Action::Cut => app.yank_file(PasteMode::Move),
+        Action::Yank => app.yank_file(PasteMode::Copy),
+        Action::Paste => app.paste(None),
+        Action::SortName => app.sort_by(ListOrder::Name),
+        Action::SortNameReverse => app.sort_by(ListOrder::NameReverse),
+        Action::SortCreated => app.sort_by(ListOrder::Created),
+        Action::SortCreatedReverse => app.sort_by(ListOrder::CreatedReverse),
+        Action::SortModified => app.sort_by(ListOrder::Modified),
+        Action::SortModifiedReverse => app.sort_by(ListOrder::ModifiedReverse),
+        Action::SortDirsFirst => app.sort_by(ListOrder::DirsFirst),
+        Action::SortFilesFirst => app.sort_by(ListOrder::FilesFirst),
+        Action::Rename => match app.get_selected() {
+            Some(selected) => {
+                let selected = selected.path.file_name().unwrap().to_str().unwrap();
+                app.input_mode = InputMode::Input(format!(":rename {selected}"));
+                app.set_message(app.input_mode.get_str());
+            }
+            None => {
+                app.set_message("nothing is selected");
+            }
+        },
+        Action::Command => {
+            app.input_mode = InputMode::Input(":".to_string());
+            app.set_message(app.input_mode.get_str());
+        }
+        Action::ToggleHidden => app.toggle_hidden_files(),
+        Action::Tag => app.toggle_tag_item(),
+        Action::TagCount => {
+            // TODO needs refinement to only include tags in cwd
+            app.set_message(format!("Tags count: {}", app.config.tags.len()))
+        }
+        Action::Undo => app.undo(false),
+        Action::Redo => app.undo(true),
+        Action::Search => {
+            app.input_mode = InputMode::Input("/".to_string());
+            app.set_message(app.input_mode.get_str());
+        }
+        Action::SearchNext => app.search_next(true),
+        Action::SearchPrevious => app.search_next(false),
+        Action::Find => {
+            app.input_mode = InputMode::Input(":find ".to_string());
+            app.set_message(app.input_mode.get_str());
+        }
+        Action::Filter => {
+            let pattern = match &app.filter {
+                Some((dir, pattern, _)) if *dir == app.pwd => pattern.to_string(),
+                _ => String::new(),
+            };
+            app.input_mode = InputMode::Input(format!(":filter {pattern}"));
+            app.set_message(app.input_mode.get_str());
+        }
+        Action::Select => {
+            // select the current thing
+            match app.get_selected() {
+                Some(selected) => {
+                    app.input_mode = InputMode::Select(vec![selected.path.to_path_buf()]);
+                    app.go_down();
+                    app.set_message("Selected 1 item.")
+                }
+                None => app.set_message("nothing is selected"),
+            };
+        }
+        // the keymap keeps these out of normal mode
+        Action::Cancel
+        | Action::Execute
+        | Action::DeleteChar
+        | Action::NextCandidate
+        | Action::PreviousCandidate
+        | Action::PauseJob
+        | Action::CancelJob => {}
+    }
+    false
+}
+
+// the lists that pop up, like :jobs and :trash-list
+fn panel(app: &mut App, action: Action) {
+    let panel = match &mut app.input_mode {
+        InputMode::Panel(panel) => panel,
+        _ => return,
+    };
+    let index = panel.list.state.selected().unwrap_or(0);
+    match (action, &panel.kind) {
+        (Action::GoDown, _) => panel.list.next(),
+        (Action::GoUp, _) => panel.list.prev(),
+        (Action::GoTop, _) => panel
+            .list
+            .state
+            .select((!panel.list.items.is_empty()).then_some(0)),
+        (Action::GoBottom, _) => panel
+            .list
+            .state
+            .select(panel.list.items.len().checked_sub(1)),
+        (Action::Execute, _) => app.panel_enter(),
+        (Action::Cancel, _) => {
+            app.input_mode = InputMode::Normal;
+            app.set_message("");
+        }
+        (Action::PauseJob, PanelKind::Jobs) => app.jobs.toggle_pause(index),
+        (Action::CancelJob, PanelKind::Jobs) => app.jobs.cancel(index),
+        _ => {}
+    }
+}
+
+fn select(app: &mut App, action: Action) -> bool {
+    let v = match &app.input_mode {
+        InputMode::Select(v) => v.to_vec(),
+        _ => return false,
+    };
+    let mut len = v.len();
+    match action {
+        Action::Quit => return true,
+        Action::Select => {
+            // select the current thing
+            match app.get_selected() {
+                Some(selected) => {
+                    let selected = &selected.path;
+                    if !v.contains(selected) {
+                        app.input_mode.push_path(selected.to_path_buf());
+                        len += 1;
+                        app.go_down();
+                    } else if let Some(index) = v.iter().position(|x| x == selected) {
+                        app.input_mode.remove_path(index);
+                        len -= 1;
+                        app.go_down();
                     }
+                    // app.toggle_select();
+                    app.set_message(format!("Selected {} items.", len));
                 }
+                None => app.set_message("nothing is selected"),
+            };
+        }
+        Action::GoLeft => {
+            app.go_left();
+            app.input_mode = InputMode::Normal;
+        }
+        Action::GoRight => {
+            app.go_right();
+            app.input_mode = InputMode::Normal;
+        }
+        Action::GoUp => {
+            // go up
+            app.go_up();
+            app.set_message(format!("Selected {} items.", len));
+        }
+        Action::GoDown => {
+            // go down
+            app.go_down();
+            app.set_message(format!("Selected {} items.", len));
+        }
+        Action::Cancel => {
+            app.set_message("canceled");
+            app.refresh_right_column();
+            app.input_mode = InputMode::Normal;
+        }
+        Action::ToggleHidden => {
+            app.toggle_hidden_files();
+            app.set_message(format!("Selected {} items.", len));
+        }
+        Action::Command => {
+            // commands like :bulkrename work on the select set
+            app.selection = v;
+            app.input_mode = InputMode::Input(":".to_string());
+            app.set_message(app.input_mode.get_str());
+        }
+        Action::Cut => {
+            app.yank_register.register = v;
+            app.yank_register.mode = PasteMode::Move;
+            app.input_mode = InputMode::Normal;
+            app.set_message("files in register, type p to paste")
+        }
+        Action::Trash => {
+            app.input_mode = InputMode::Normal;
+            app.trash_selection(&v)
+        }
+        Action::Delete => {
+            app.input_mode = InputMode::Confirmation(Confirm::DeleteSelection(v), 'Y');
+            app.set_message("are you sure you want to permanently delete all selected items? [Y/n]")
+        }
+        Action::Yank => {
+            app.yank_register.register = v;
+            app.yank_register.mode = PasteMode::Copy;
+            app.input_mode = InputMode::Normal;
+            app.set_message("files in register, type p to paste")
+        }
+        _ => {}
+    }
+    false
+}
+
+fn input(app: &mut App, action: Action) {
+    let typed = app.input_mode.get_str();
+    match action {
+        Action::Execute => {
+            // execute the command somehow
+            app.execute();
+            app.selection.clear();
+            app.finder.items.clear();
+        }
+        Action::DeleteChar => {
+            app.input_mode.pop_char();
+            app.set_message(app.input_mode.get_str());
+            // a bit of a special case here for find
+            if app.input_mode.get_str().starts_with(":find") {
+                app.inc_find(&app.input_mode.get_str());
+            } else if app.input_mode.get_str().starts_with('/') {
+                app.inc_search(&app.input_mode.get_str());
+            } else if app.input_mode.get_str().starts_with(":filter") {
+                app.refresh_middle_column();
+                app.refresh_right_column();
+                app.set_metadata();
+            }
+        }
+        Action::Cancel => {
+            app.set_message("canceled");
+            app.input_mode = InputMode::Normal;
+            // back to how it was before typing
+            if typed.starts_with(":filter") {
+                app.refresh_middle_column();
+                app.set_metadata();
+            }
+            app.refresh_right_column();
+            app.selection.clear();
+            app.finder.items.clear();
+        }
+        // going through the :find candidates
+        Action::NextCandidate if typed.starts_with(":find ") => {
+            app.finder.next();
+            app.found();
+        }
+        Action::PreviousCandidate if typed.starts_with(":find ") => {
+            app.finder.prev();
+            app.found();
+        }
+        _ => {}
+    }
+}
+
+fn type_char(app: &mut App, c: char) {
+    app.input_mode.push_char(c);
+    app.set_message(app.input_mode.get_str());
+    if app.input_mode.get_str().starts_with('/') {
+        // incrementally highlight the found thing
+        app.inc_search(&app.input_mode.get_str());
+    } else if app.input_mode.get_str().starts_with(":find ") {
+        // incrementally highlight the found thing
+        app.inc_find(&app.input_mode.get_str());
+    } else if app.input_mode.get_str().starts_with(":filter ") {
+        // narrow things down while typing
+        app.refresh_middle_column();
+        app.refresh_right_column();
+        app.set_metadata();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use testdir::TestDir;
+
+    fn names(app: &App) -> Vec<String> {
+        app.middle_column
+            .items
+            .iter()
+            .map(|item| item.path.file_name().unwrap().to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn show_hidden_is_the_opposite_of_hiding() {
+        let root = TestDir::new("hidden");
+        let (dir, config) = (root.join("listed"), root.join("config"));
+        fs::create_dir(&dir).unwrap();
+        fs::write(dir.join(".dotfile"), "").unwrap();
+        fs::write(dir.join("file"), "").unwrap();
+
+        // a new config hides them
+        let (cfg, _) = config::load(&config).unwrap();
+        let mut app = App::new(dir.clone(), cfg);
+        assert_eq!(names(&app), ["file"]);
+        // and toggling is what gets saved
+        app.toggle_hidden_files();
+        assert_eq!(names(&app).len(), 2);
+        config::store(&app.config, &config).unwrap();
+        let text = fs::read_to_string(config.join("lga.toml")).unwrap();
+        assert!(text.contains("show_hidden = true"));
+
+        let (cfg, _) = config::load(&config).unwrap();
+        let mut app = App::new(dir.clone(), cfg);
+        assert_eq!(names(&app).len(), 2);
+        app.toggle_hidden_files();
+        assert_eq!(names(&app), ["file"]);
+        assert!(!app.config.show_hidden);
+    }
+
+    #[test]
+    fn grep_hits_past_the_preview_stay_on_screen() {
+        let root = TestDir::new("seek");
+        let (dir, config) = (root.join("listed"), root.join("config"));
+        fs::create_dir(&dir).unwrap();
+        let text: Vec<String> = (1..=1000).map(|n| format!("line {n}")).collect();
+        fs::write(dir.join("long.txt"), text.join("\n")).unwrap();
+        let (cfg, _) = config::load(&config).unwrap();
+        let mut app = App::new(dir.clone(), cfg);
+        app.middle_column.state.select(Some(0));
+        app.refresh_right_column();
+        // the preview loads in the background
+        let mut height = None;
+        for _ in 0..500 {
+            app.tick();
+            height = app
+                .get_selected()
+                .and_then(|s| s.preview.as_ref())
+                .map(|p| p.height());
+            if height.is_some() {
+                break;
             }
+            std::thread::sleep(Duration::from_millis(10));
         }
+        let height = height.expect("the preview never showed up");
+        let regex = Regex::new("line").unwrap();
+        app.mark = Some((dir.join("long.txt"), 100, regex.clone()));
+        app.seek = Some(100);
+        app.seek_mark();
+        assert_eq!(app.scroll, 96);
+        assert_eq!(app.seek, None);
+        // further down than the preview goes
+        app.mark = Some((dir.join("long.txt"), 900, regex));
+        app.seek = Some(900);
+        app.seek_mark();
+        assert_eq!(app.scroll as usize, height - 4);
+        assert!(app.message.contains("line 900 is past the first"));
     }
 }
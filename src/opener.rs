@@ -0,0 +1,240 @@
+use file_format::{FileFormat, Kind};
+use globset::Glob;
+use serde::{Deserialize, Serialize};
+use std::{
+    env,
+    io::ErrorKind,
+    path::Path,
+    process::{Command, Stdio},
+    thread,
+};
+
+// what a file has to look like for a rule to apply. every criteria that is
+// set has to match, none at all matches everything. the previewers use these
+// too
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Criteria {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<Kind>,
+    // full media type like "image/png" or a whole family like "image/*"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mime: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ext: Vec<String>,
+    // matched against the file name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub glob: Option<String>,
+}
+
+impl Criteria {
+    pub fn kind(kind: Kind) -> Criteria {
+        Criteria {
+            kind: Some(kind),
+            ..Default::default()
+        }
+    }
+
+    pub fn matches(&self, path: &Path, format: &FileFormat) -> bool {
+        let kind = self.kind.is_none_or(|kind| kind == format.kind());
+        let mime = self
+            .mime
+            .as_ref()
+            .is_none_or(|mime| match mime.strip_suffix("/*") {
+                Some(family) => format
+                    .media_type()
+                    .split_once('/')
+                    .is_some_and(|(f, _)| f.eq_ignore_ascii_case(family)),
+                None => mime.eq_ignore_ascii_case(format.media_type()),
+            });
+        let ext = self.ext.is_empty()
+            || path
+                .extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| self.ext.iter().any(|x| x.eq_ignore_ascii_case(e)));
+        let glob =
+            self.glob
+                .as_ref()
+                .is_none_or(|glob| match (Glob::new(glob), path.file_name()) {
+                    (Ok(glob), Some(name)) => glob.compile_matcher().is_match(name),
+                    _ => false,
+                });
+        kind && mime && ext && glob
+    }
+}
+
+// a rule from the [openers] table. commands are templates where `{}` or `%f`
+// get replaced with the file, if neither is there the file is appended as the
+// last argument. terminal programs get the whole terminal while they run, lga
+// waits for them to exit
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Rule {
+    #[serde(flatten)]
+    pub criteria: Criteria,
+    pub commands: Vec<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub terminal: bool,
+}
+
+impl Rule {
+    fn new(kind: Kind, command: &str) -> Rule {
+        Rule {
+            criteria: Criteria::kind(kind),
+            commands: vec![command.to_string()],
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Openers {
+    // tried when no rule matched or none of the rule's commands could be found
+    pub fallback: Vec<String>,
+    // what text files get opened with, $VISUAL, $EDITOR then $PAGER otherwise
+    pub editor: Option<String>,
+    // last, the toml confy writes wants plain values before tables
+    pub rules: Vec<Rule>,
+}
+
+pub struct Candidate<'a> {
+    pub command: &'a str,
+    pub terminal: bool,
+}
+
+pub enum Opened {
+    // started and left running on its own
+    Background(String),
+    // has to run in the foreground with the tui suspended
+    Foreground(Command),
+}
+
+impl Default for Openers {
+    fn default() -> Self {
+        Openers {
+            rules: vec![
+                Rule::new(Kind::Book, "zathura {}"),
+                Rule::new(Kind::Document, "zathura {}"),
+                Rule::new(Kind::Image, "sxiv {}"),
+                Rule::new(Kind::Video, "vlc {}"),
+            ],
+            fallback: vec!["xdg-open {}".to_string()],
+            editor: None,
+        }
+    }
+}
+
+impl Openers {
+    // every command worth trying for this file, in order. text and
+    // application files go to the editor instead of the fallback, whatever
+    // xdg-open picks for those tends to be a terminal program anyway
+    pub fn candidates<'a>(
+        &'a self,
+        path: &Path,
+        format: &FileFormat,
+        editor: &'a str,
+    ) -> Vec<Candidate<'a>> {
+        let mut candidates: Vec<Candidate> = self
+            .rules
+            .iter()
+            .filter(|rule| rule.criteria.matches(path, format))
+            .flat_map(|rule| {
+                rule.commands.iter().map(|command| Candidate {
+                    command,
+                    terminal: rule.terminal,
+                })
+            })
+            .collect();
+        match format.kind() {
+            Kind::Text | Kind::Application => candidates.push(Candidate {
+                command: editor,
+                terminal: true,
+            }),
+            _ => candidates.extend(self.fallback.iter().map(|command| Candidate {
+                command,
+                terminal: false,
+            })),
+        }
+        candidates
+    }
+
+    pub fn editor(&self) -> String {
+        match &self.editor {
+            Some(editor) => editor.to_string(),
+            None => ["VISUAL", "EDITOR", "PAGER"]
+                .iter()
+                .find_map(|var| env::var(var).ok().filter(|v| !v.is_empty()))
+                .unwrap_or("vi".to_string()),
+        }
+    }
+}
+
+// looks the program up in $PATH the way spawning it would
+fn installed(program: &str) -> bool {
+    if program.contains('/') {
+        return Path::new(program).is_file();
+    }
+    match env::var_os("PATH") {
+        Some(paths) => env::split_paths(&paths).any(|dir| dir.join(program).is_file()),
+        None => false,
+    }
+}
+
+// turns a command template into the program and its arguments
+pub fn build(template: &str, path: &Path) -> Option<Command> {
+    let path = path.to_str()?;
+    let mut words = shell_words::split(template).ok()?.into_iter();
+    let mut command = Command::new(words.next()?);
+    let mut placeholder = false;
+    for word in words {
+        if word.contains("{}") || word.contains("%f") {
+            placeholder = true;
+            command.arg(word.replace("{}", path).replace("%f", path));
+        } else {
+            command.arg(word);
+        }
+    }
+    if !placeholder {
+        command.arg(path);
+    }
+    Some(command)
+}
+
+// picks the first candidate that exists. background programs are spawned right
+// away, terminal ones are handed back so they can be run in the foreground
+pub fn open(candidates: &[Candidate], path: &Path) -> Result<Opened, String> {
+    let mut missing = vec![];
+    for candidate in candidates {
+        let mut command = match build(candidate.command, path) {
+            Some(command) => command,
+            None => return Err(format!("bad opener command: {}", candidate.command)),
+        };
+        let program = command.get_program().to_string_lossy().to_string();
+        if candidate.terminal {
+            if installed(&program) {
+                return Ok(Opened::Foreground(command));
+            }
+            missing.push(program);
+            continue;
+        }
+        match command
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(mut child) => {
+                // somebody has to reap it
+                thread::spawn(move || child.wait());
+                return Ok(Opened::Background(program));
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => missing.push(program),
+            Err(e) => return Err(format!("failed to run {program}: {e}")),
+        }
+    }
+    Err(format!(
+        "none of these are installed: {}",
+        missing.join(", ")
+    ))
+}
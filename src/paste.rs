@@ -0,0 +1,175 @@
+// deciding what happens when a pasted item lands on a name that is taken
+use crate::PasteMode;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashSet, VecDeque},
+    path::{Path, PathBuf},
+};
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Conflict {
+    // the old one goes to the trash
+    Overwrite,
+    Skip,
+    // the pasted one gets a free name
+    Rename,
+    Ask,
+}
+
+impl Conflict {
+    pub fn parse(s: &str) -> Option<Conflict> {
+        match s {
+            "overwrite" => Some(Conflict::Overwrite),
+            "skip" => Some(Conflict::Skip),
+            "rename" => Some(Conflict::Rename),
+            "ask" => Some(Conflict::Ask),
+            _ => None,
+        }
+    }
+}
+
+// how free names look, `name (1).ext` or `name_1.ext`
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum RenameStyle {
+    Parens,
+    Underscore,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct PasteConfig {
+    pub conflict: Conflict,
+    pub rename: RenameStyle,
+}
+
+impl Default for PasteConfig {
+    fn default() -> Self {
+        PasteConfig {
+            conflict: Conflict::Ask,
+            rename: RenameStyle::Parens,
+        }
+    }
+}
+
+// the first name like dst that is not taken on disk or by the paste itself
+pub fn free_name(dst: &Path, style: RenameStyle, taken: &HashSet<PathBuf>) -> PathBuf {
+    let name = dst.file_name().unwrap_or_default().to_string_lossy();
+    // dotfiles and directories keep the number at the end
+    let (stem, ext) = match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() && !dst.is_dir() => (stem, format!(".{ext}")),
+        _ => (name.as_ref(), String::new()),
+    };
+    (1..)
+        .map(|n| match style {
+            RenameStyle::Parens => dst.with_file_name(format!("{stem} ({n}){ext}")),
+            RenameStyle::Underscore => dst.with_file_name(format!("{stem}_{n}{ext}")),
+        })
+        .find(|p| p.symlink_metadata().is_err() && !taken.contains(p))
+        .unwrap_or_default()
+}
+
+// a paste going through its items, stops at every conflict it has to ask
+// about
+pub struct Resolving {
+    pub mode: PasteMode,
+    policy: Conflict,
+    style: RenameStyle,
+    pending: VecDeque<(PathBuf, PathBuf)>,
+    // the one being asked about
+    current: Option<(PathBuf, PathBuf)>,
+    pub resolved: Vec<(PathBuf, PathBuf)>,
+    // existing items that get replaced
+    pub replace: Vec<PathBuf>,
+    pub skipped: usize,
+    taken: HashSet<PathBuf>,
+}
+
+impl Resolving {
+    pub fn new(
+        mode: PasteMode,
+        items: Vec<(PathBuf, PathBuf)>,
+        policy: Conflict,
+        style: RenameStyle,
+    ) -> Resolving {
+        Resolving {
+            mode,
+            policy,
+            style,
+            pending: items.into(),
+            current: None,
+            resolved: vec![],
+            replace: vec![],
+            skipped: 0,
+            taken: HashSet::new(),
+        }
+    }
+
+    fn apply(&mut self, policy: Conflict, src: PathBuf, dst: PathBuf) {
+        match policy {
+            // that would throw away the thing being pasted
+            Conflict::Overwrite if src.starts_with(&dst) => self.skipped += 1,
+            Conflict::Overwrite => {
+                // two pasted items with the same name, the last one wins
+                if dst.symlink_metadata().is_ok() && !self.replace.contains(&dst) {
+                    self.replace.push(dst.to_path_buf());
+                }
+                self.resolved.retain(|(_, d)| *d != dst);
+                self.taken.insert(dst.to_path_buf());
+                self.resolved.push((src, dst));
+            }
+            Conflict::Skip => self.skipped += 1,
+            Conflict::Rename => {
+                let dst = free_name(&dst, self.style, &self.taken);
+                self.taken.insert(dst.to_path_buf());
+                self.resolved.push((src, dst));
+            }
+            Conflict::Ask => self.current = Some((src, dst)),
+        }
+    }
+
+    // goes on until everything is resolved (true) or theres a question
+    pub fn advance(&mut self) -> bool {
+        while self.current.is_none() {
+            let (src, dst) = match self.pending.pop_front() {
+                Some(item) => item,
+                None => return true,
+            };
+            if dst.symlink_metadata().is_ok() || self.taken.contains(&dst) {
+                self.apply(self.policy, src, dst);
+            } else {
+                self.taken.insert(dst.to_path_buf());
+                self.resolved.push((src, dst));
+            }
+        }
+        false
+    }
+
+    pub fn question(&self) -> String {
+        match &self.current {
+            Some((_, dst)) => format!(
+                "{} already exists: [o]verwrite, [s]kip, [r]ename, uppercase for all",
+                dst.file_name().unwrap_or_default().to_string_lossy()
+            ),
+            None => String::new(),
+        }
+    }
+
+    // false when c isnt an answer
+    pub fn answer(&mut self, c: char) -> bool {
+        let policy = match c.to_ascii_lowercase() {
+            'o' => Conflict::Overwrite,
+            's' => Conflict::Skip,
+            'r' => Conflict::Rename,
+            _ => return false,
+        };
+        if c.is_ascii_uppercase() {
+            self.policy = policy;
+        }
+        if let Some((src, dst)) = self.current.take() {
+            self.apply(policy, src, dst);
+        }
+        true
+    }
+}
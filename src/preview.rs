@@ -0,0 +1,457 @@
+// what goes in the right column for files. a table of rules picks the
+// previewers for a file, the first one that has something to show wins. the
+// built in ones are called by name, anything else is a command whose output
+// becomes the preview, like ranger's scope.sh
+use crate::{
+    highlight::{Highlighter, DEFAULT_THEME},
+    ls,
+    opener::{self, Criteria},
+    vfs, Item, ListOrder,
+};
+use file_format::{FileFormat, Kind};
+use flate2::read::GzDecoder;
+use humansize::{format_size, DECIMAL};
+use lru::LruCache;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::{self, Read},
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+use tui::text::Text as Lines;
+use xz2::read::XzDecoder;
+
+// nothing reads more than this much of a file, unless the config says
+// otherwise
+const HEAD: usize = 64 * 1024;
+// or shows more lines than this
+const LINES: usize = 500;
+// external previewers get killed after this long
+const TIMEOUT: Duration = Duration::from_secs(2);
+// archive members bigger than this dont get extracted just for a preview
+const EXTRACT: u64 = 64 << 20;
+// how many previews are kept around
+const CACHED: usize = 256;
+
+pub trait Previewer {
+    // None when this previewer has nothing to say about the file, the next
+    // one gets a go then
+    fn preview(&self, path: &Path, format: &FileFormat) -> Option<String>;
+
+    // whether the preview is source worth highlighting
+    fn highlighted(&self) -> bool {
+        false
+    }
+}
+
+// the first lines of text files
+struct Text(usize);
+// the first bytes of anything, xxd style
+struct Hex;
+// zip, tar and compressed tars
+struct Archive(usize);
+// what kind of image and how big
+struct Image;
+// how many things are in a directory
+struct Directory;
+// a command from the config, `{}` and `%f` work like they do for openers
+struct External(String, usize);
+
+// the head of whatever reader as text, None if its binary
+fn head(reader: impl Read, limit: usize) -> Option<String> {
+    let mut buffer = vec![];
+    reader.take(limit as u64).read_to_end(&mut buffer).ok()?;
+    if buffer.contains(&0) {
+        return None;
+    }
+    let text = match std::str::from_utf8(&buffer) {
+        Ok(text) => text,
+        // cut off in the middle of a character
+        Err(e) if e.error_len().is_none() => {
+            std::str::from_utf8(&buffer[..e.valid_up_to()]).ok()?
+        }
+        Err(_) => return None,
+    };
+    // tabs dont take up any room in the terminal otherwise
+    let lines: Vec<String> = text
+        .lines()
+        .take(LINES)
+        .map(|l| l.replace('\t', "    "))
+        .collect();
+    Some(lines.join("\n"))
+}
+
+impl Previewer for Text {
+    fn preview(&self, path: &Path, _: &FileFormat) -> Option<String> {
+        head(File::open(path).ok()?, self.0)
+    }
+
+    fn highlighted(&self) -> bool {
+        true
+    }
+}
+
+impl Previewer for Hex {
+    fn preview(&self, path: &Path, _: &FileFormat) -> Option<String> {
+        let mut buffer = vec![];
+        File::open(path)
+            .ok()?
+            .take(LINES as u64 * 16)
+            .read_to_end(&mut buffer)
+            .ok()?;
+        let lines: Vec<String> = buffer
+            .chunks(16)
+            .enumerate()
+            .map(|(i, chunk)| {
+                let hex: Vec<String> = chunk.iter().map(|b| format!("{b:02x}")).collect();
+                let ascii: String = chunk
+                    .iter()
+                    .map(|&b| match b.is_ascii_graphic() || b == b' ' {
+                        true => b as char,
+                        false => '.',
+                    })
+                    .collect();
+                format!("{:08x}  {:<47}  {ascii}", i * 16, hex.join(" "))
+            })
+            .collect();
+        Some(lines.join("\n"))
+    }
+}
+
+fn tar_listing(reader: impl Read) -> Option<Vec<String>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut lines = vec![];
+    for entry in archive.entries().ok()?.take(LINES) {
+        let entry = entry.ok()?;
+        lines.push(format!(
+            "{:>10}  {}",
+            format_size(entry.size(), DECIMAL),
+            entry.path().ok()?.display()
+        ));
+    }
+    Some(lines)
+}
+
+impl Previewer for Archive {
+    fn preview(&self, path: &Path, format: &FileFormat) -> Option<String> {
+        let file = File::open(path).ok()?;
+        let lines = match format {
+            FileFormat::Zip => {
+                let mut archive = zip::ZipArchive::new(file).ok()?;
+                let mut lines = vec![format!("{} entries", archive.len())];
+                for i in 0..archive.len().min(LINES) {
+                    let entry = archive.by_index_raw(i).ok()?;
+                    lines.push(format!(
+                        "{:>10}  {}",
+                        format_size(entry.size(), DECIMAL),
+                        entry.name()
+                    ));
+                }
+                lines
+            }
+            FileFormat::TapeArchive => tar_listing(file)?,
+            // a compressed tar, or else just some compressed file which gets
+            // shown like it wasnt
+            FileFormat::Gzip => match tar_listing(GzDecoder::new(file)) {
+                Some(lines) => lines,
+                None => return head(GzDecoder::new(File::open(path).ok()?), self.0),
+            },
+            FileFormat::Xz => match tar_listing(XzDecoder::new(file)) {
+                Some(lines) => lines,
+                None => return head(XzDecoder::new(File::open(path).ok()?), self.0),
+            },
+            _ => return None,
+        };
+        Some(lines.join("\n"))
+    }
+}
+
+impl Previewer for Image {
+    fn preview(&self, path: &Path, format: &FileFormat) -> Option<String> {
+        let mut lines = vec![format.name().to_string(), format.media_type().to_string()];
+        if let Ok(size) = imagesize::size(path) {
+            lines.push(format!("{}x{}", size.width, size.height));
+        }
+        lines.push(format_size(path.metadata().ok()?.len(), DECIMAL));
+        Some(lines.join("\n"))
+    }
+}
+
+impl Previewer for Directory {
+    fn preview(&self, path: &Path, _: &FileFormat) -> Option<String> {
+        let (mut dirs, mut files, mut bytes) = (0, 0, 0);
+        for entry in fs::read_dir(path).ok()?.flatten() {
+            match entry.metadata() {
+                Ok(metadata) if metadata.is_dir() => dirs += 1,
+                Ok(metadata) => {
+                    files += 1;
+                    bytes += metadata.len();
+                }
+                Err(_) => files += 1,
+            }
+        }
+        Some(format!(
+            "{dirs} dirs, {files} files, {}",
+            format_size(bytes, DECIMAL)
+        ))
+    }
+}
+
+impl Previewer for External {
+    fn preview(&self, path: &Path, _: &FileFormat) -> Option<String> {
+        let mut child = opener::build(&self.0, path)?
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()?;
+        // read on the side so a chatty command cant get stuck on a full pipe,
+        // once theres enough the pipe gets closed on it
+        let stdout = child.stdout.take()?;
+        let limit = self.1;
+        let reader = thread::spawn(move || {
+            let mut buffer = vec![];
+            let _ = stdout.take(limit as u64).read_to_end(&mut buffer);
+            buffer
+        });
+        let deadline = Instant::now() + TIMEOUT;
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break status,
+                Ok(None) if Instant::now() < deadline => thread::sleep(Duration::from_millis(10)),
+                _ => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    // not waiting on the reader, whatever the command left
+                    // behind might hold on to the pipe
+                    return None;
+                }
+            }
+        };
+        let output = reader.join().ok()?;
+        // a failing command means it has nothing for this file, unless it was
+        // just cut off for talking too much
+        match status {
+            status if status.success() || output.len() == self.1 => {
+                let output = String::from_utf8_lossy(&output);
+                Some(output.lines().take(LINES).collect::<Vec<&str>>().join("\n"))
+            }
+            _ => None,
+        }
+    }
+}
+
+fn previewer(name: &str, limit: usize) -> Box<dyn Previewer> {
+    match name {
+        "text" => Box::new(Text(limit)),
+        "hex" => Box::new(Hex),
+        "archive" => Box::new(Archive(limit)),
+        "image" => Box::new(Image),
+        "directory" => Box::new(Directory),
+        command => Box::new(External(command.to_string(), limit)),
+    }
+}
+
+// a rule from the [previews] table, criteria work like they do for openers
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Rule {
+    #[serde(flatten)]
+    pub criteria: Criteria,
+    pub previewers: Vec<String>,
+}
+
+impl Rule {
+    fn new(kind: Kind, previewer: &str) -> Rule {
+        Rule {
+            criteria: Criteria::kind(kind),
+            previewers: vec![previewer.to_string()],
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Previews {
+    // tried after the rules
+    pub fallback: Vec<String>,
+    // for directories, these show up on top of the listing
+    pub directory: Vec<String>,
+    // a syntect theme like "Solarized (dark)" or the path to a .tmTheme
+    pub theme: String,
+    // how much of a file previews get to read, in bytes
+    pub limit: usize,
+    // last, like the opener rules
+    pub rules: Vec<Rule>,
+}
+
+impl Default for Previews {
+    fn default() -> Self {
+        Previews {
+            rules: vec![
+                Rule::new(Kind::Archive, "archive"),
+                Rule::new(Kind::Compression, "archive"),
+                Rule::new(Kind::Image, "image"),
+                Rule::new(Kind::Text, "text"),
+            ],
+            fallback: vec!["text".to_string(), "hex".to_string()],
+            directory: vec!["directory".to_string()],
+            theme: DEFAULT_THEME.to_string(),
+            limit: HEAD,
+        }
+    }
+}
+
+impl Previews {
+    pub fn preview(
+        &self,
+        path: &Path,
+        highlighter: &Highlighter,
+    ) -> io::Result<Option<Lines<'static>>> {
+        let format = FileFormat::from_file(path)?;
+        Ok(self
+            .rules
+            .iter()
+            .filter(|rule| rule.criteria.matches(path, &format))
+            .flat_map(|rule| &rule.previewers)
+            .chain(&self.fallback)
+            .find_map(|name| {
+                let previewer = previewer(name, self.limit);
+                let preview = previewer.preview(path, &format)?;
+                Some(match previewer.highlighted() {
+                    true => highlighter.highlight(path, preview),
+                    false => Lines::raw(preview),
+                })
+            }))
+    }
+
+    // goes on top of directory listings
+    pub fn summary(&self, path: &Path) -> Option<String> {
+        self.directory
+            .iter()
+            .find_map(|name| previewer(name, self.limit).preview(path, &FileFormat::default()))
+    }
+}
+
+// what the right column shows for the selected item
+#[derive(Clone)]
+pub enum Preview {
+    // the directory listing, tags are left for the ui thread to fill in, and
+    // the summary that goes on top
+    Listing(Vec<Item<PathBuf, Lines<'static>>>, String),
+    Text(Option<Lines<'static>>),
+}
+
+// a preview is good for as long as the file wasnt modified, listings also
+// depend on how they are listed
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct Key {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    hidden: bool,
+    order: ListOrder,
+}
+
+fn load(previews: &Previews, highlighter: &Highlighter, key: &Key) -> Preview {
+    if vfs::is_dir(&key.path) {
+        return Preview::Listing(
+            ls(&key.path, key.hidden, &key.order, &[]),
+            previews.summary(&key.path).unwrap_or_default(),
+        );
+    }
+    // things in archives get previewed from an extracted copy
+    let path = match vfs::split(&key.path) {
+        Some(_) if vfs::len(&key.path).unwrap_or(0) > EXTRACT => {
+            let message = "too big to preview from inside an archive";
+            return Preview::Text(Some(Lines::raw(message)));
+        }
+        Some(_) => vfs::materialize(&key.path),
+        None => Ok(key.path.to_path_buf()),
+    };
+    match path.and_then(|path| previews.preview(&path, highlighter)) {
+        Ok(preview) => Preview::Text(preview),
+        Err(e) => Preview::Text(Some(Lines::raw(format!("problem reading file: {e}")))),
+    }
+}
+
+// previews get made on a worker thread so holding j on a slow disk doesnt
+// hold up the ui, and end up in a cache so going back and forth is instant
+pub struct Loader {
+    sender: Sender<Key>,
+    results: Receiver<(Key, Preview)>,
+    cache: LruCache<Key, Preview>,
+    // what the right column is waiting for
+    wanted: Option<Key>,
+}
+
+impl Loader {
+    pub fn new(previews: Previews) -> Loader {
+        let (sender, requests) = mpsc::channel::<Key>();
+        let (report, results) = mpsc::channel();
+        thread::spawn(move || {
+            let highlighter = Highlighter::new(&previews.theme);
+            while let Ok(key) = requests.recv() {
+                // only the newest request matters, the rest are for items the
+                // cursor already went past
+                let key = requests.try_iter().last().unwrap_or(key);
+                let preview = load(&previews, &highlighter, &key);
+                if report.send((key, preview)).is_err() {
+                    break;
+                }
+            }
+        });
+        Loader {
+            sender,
+            results,
+            cache: LruCache::new(NonZeroUsize::new(CACHED).unwrap()),
+            wanted: None,
+        }
+    }
+
+    // the preview right away if its cached, otherwise it gets asked for and
+    // shows up in poll later
+    pub fn request(&mut self, path: &Path, hidden: bool, order: ListOrder) -> Option<Preview> {
+        let key = Key {
+            path: path.to_path_buf(),
+            modified: vfs::modified(path),
+            hidden,
+            order,
+        };
+        if let Some(preview) = self.cache.get(&key) {
+            self.wanted = None;
+            return Some(preview.clone());
+        }
+        self.wanted = Some(key.clone());
+        let _ = self.sender.send(key);
+        None
+    }
+
+    // everything that came in goes to the cache, the path and preview come
+    // back if its the one being waited for
+    pub fn poll(&mut self) -> Option<(PathBuf, Preview)> {
+        let mut ready = None;
+        for (key, preview) in self.results.try_iter() {
+            if self.wanted.as_ref() == Some(&key) {
+                self.wanted = None;
+                ready = Some((key.path.to_path_buf(), preview.clone()));
+            }
+            self.cache.put(key, preview);
+        }
+        ready
+    }
+
+    pub fn loading(&self) -> bool {
+        self.wanted.is_some()
+    }
+
+    // not waiting for anything anymore, whatever is running still ends up in
+    // the cache
+    pub fn cancel(&mut self) {
+        self.wanted = None;
+    }
+}
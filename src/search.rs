@@ -0,0 +1,291 @@
+// looking for names or lines of text under a directory on a worker thread,
+// the hits come in while the walk goes on. hidden files and .gitignore are
+// respected
+use file_format::{FileFormat, Kind};
+use globset::{GlobBuilder, GlobMatcher};
+use ignore::WalkBuilder;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, TryRecvError},
+        Arc,
+    },
+    thread,
+};
+
+// nobody scrolls past this many
+const LIMIT: usize = 10_000;
+// grep lines get cut off here
+const WIDTH: usize = 200;
+
+// for / and n/N in the middle column
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchConfig {
+    // all lowercase patterns ignore case and the rest dont, without it case
+    // never matters
+    pub smart_case: bool,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        SearchConfig { smart_case: true }
+    }
+}
+
+#[derive(Clone)]
+enum Pattern {
+    Substring(String),
+    Glob(GlobMatcher),
+    Regex(Regex),
+}
+
+#[derive(Clone)]
+pub struct Matcher {
+    pattern: Pattern,
+    // patterns with a / in them match the whole relative path, the rest just
+    // the name
+    path: bool,
+    ignore_case: bool,
+}
+
+impl Matcher {
+    // `re:` in front makes it a regex, `*`, `?`, `[` or `{` a glob, anything
+    // else is looked for anywhere in the name. all lowercase ignores case
+    pub fn parse(pattern: &str) -> Result<Matcher, String> {
+        let ignore_case = !pattern.chars().any(char::is_uppercase);
+        let compiled = match pattern.strip_prefix("re:") {
+            Some(regex) => Pattern::Regex(
+                RegexBuilder::new(regex)
+                    .case_insensitive(ignore_case)
+                    .build()
+                    // the last line has what went wrong, the rest draws arrows
+                    .map_err(|e| e.to_string().lines().last().unwrap_or_default().to_string())?,
+            ),
+            None if pattern.contains(['*', '?', '[', '{']) => Pattern::Glob(
+                GlobBuilder::new(pattern)
+                    .case_insensitive(ignore_case)
+                    .literal_separator(true)
+                    .build()
+                    .map_err(|e| e.to_string())?
+                    .compile_matcher(),
+            ),
+            None => Pattern::Substring(pattern.to_string()),
+        };
+        Ok(Matcher {
+            pattern: compiled,
+            path: pattern.contains('/'),
+            ignore_case,
+        })
+    }
+
+    // relative is the path from wherever the search started
+    pub fn is_match(&self, relative: &Path) -> bool {
+        let subject = match self.path {
+            true => relative.as_os_str(),
+            false => relative.file_name().unwrap_or_default(),
+        };
+        let subject = subject.to_string_lossy();
+        match &self.pattern {
+            Pattern::Substring(pattern) if self.ignore_case => {
+                subject.to_lowercase().contains(pattern.as_str())
+            }
+            Pattern::Substring(pattern) => subject.contains(pattern.as_str()),
+            Pattern::Glob(glob) => glob.is_match(subject.as_ref()),
+            Pattern::Regex(regex) => regex.is_match(&subject),
+        }
+    }
+}
+
+// a name that matched, or a line in a file for :grep
+pub struct Hit {
+    pub path: PathBuf,
+    // counting from 1
+    pub line: Option<usize>,
+}
+
+pub struct Search {
+    // what it was started with, like `grep foo`
+    pub command: String,
+    pub hits: Vec<Hit>,
+    // how the hits are shown, relative to where the search started
+    pub lines: Vec<String>,
+    // what :grep looked for, to point it out in the preview
+    pub regex: Option<Regex>,
+    results: Receiver<(Hit, String)>,
+    cancel: Arc<AtomicBool>,
+    done: bool,
+}
+
+// the lines of a text file that match, binaries are left alone
+fn grep(path: &Path, relative: &Path, regex: &Regex) -> Vec<(Hit, String)> {
+    let format = FileFormat::from_file(path).unwrap_or_default();
+    if format.kind() != Kind::Text {
+        return vec![];
+    }
+    let mut reader = match File::open(path) {
+        Ok(file) => BufReader::new(file),
+        Err(_) => return vec![],
+    };
+    let mut found = vec![];
+    let mut buffer = vec![];
+    let mut number = 0;
+    while let Ok(n) = reader.read_until(b'\n', &mut buffer) {
+        if n == 0 || found.len() == LIMIT {
+            break;
+        }
+        number += 1;
+        let line = String::from_utf8_lossy(&buffer);
+        if regex.is_match(&line) {
+            let text: String = line.trim().chars().take(WIDTH).collect();
+            found.push((
+                Hit {
+                    path: path.to_path_buf(),
+                    line: Some(number),
+                },
+                format!("{}:{number}: {text}", relative.display()),
+            ));
+        }
+        buffer.clear();
+    }
+    found
+}
+
+impl Search {
+    // names under root that match pattern, see Matcher
+    pub fn names(root: &Path, pattern: &str, hidden: bool) -> Result<Search, String> {
+        let matcher = Matcher::parse(pattern)?;
+        Ok(Search::start(
+            root,
+            format!("search {pattern}"),
+            None,
+            hidden,
+            move |path, relative, dir| match matcher.is_match(relative) {
+                true => {
+                    let mut line = relative.display().to_string();
+                    if dir {
+                        line.push('/');
+                    }
+                    let hit = Hit {
+                        path: path.to_path_buf(),
+                        line: None,
+                    };
+                    vec![(hit, line)]
+                }
+                false => vec![],
+            },
+        ))
+    }
+
+    // lines in the text files under root that match regex, all lowercase
+    // ignores case
+    pub fn contents(root: &Path, regex: &str, hidden: bool) -> Result<Search, String> {
+        let compiled = RegexBuilder::new(regex)
+            .case_insensitive(!regex.chars().any(char::is_uppercase))
+            .build()
+            .map_err(|e| e.to_string().lines().last().unwrap_or_default().to_string())?;
+        let matcher = compiled.clone();
+        Ok(Search::start(
+            root,
+            format!("grep {regex}"),
+            Some(compiled),
+            hidden,
+            move |path, relative, dir| match dir {
+                true => vec![],
+                false => grep(path, relative, &matcher),
+            },
+        ))
+    }
+
+    // walks root on a worker thread, visit says what the hits for each entry
+    // are
+    fn start(
+        root: &Path,
+        command: String,
+        regex: Option<Regex>,
+        hidden: bool,
+        visit: impl Fn(&Path, &Path, bool) -> Vec<(Hit, String)> + Send + 'static,
+    ) -> Search {
+        let (sender, results) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        // hidden is whether the listing hides dotfiles, the walk does the same
+        let walk = WalkBuilder::new(root)
+            .hidden(hidden)
+            // .gitignore counts outside of git repos too
+            .require_git(false)
+            .build();
+        let root = root.to_path_buf();
+        let canceled = Arc::clone(&cancel);
+        thread::spawn(move || {
+            let mut found = 0;
+            for entry in walk.flatten() {
+                if canceled.load(Ordering::Relaxed) {
+                    break;
+                }
+                let relative = match entry.path().strip_prefix(&root) {
+                    Ok(relative) if !relative.as_os_str().is_empty() => relative,
+                    _ => continue,
+                };
+                let dir = entry.file_type().is_some_and(|t| t.is_dir());
+                for hit in visit(entry.path(), relative, dir) {
+                    if found == LIMIT || sender.send(hit).is_err() {
+                        return;
+                    }
+                    found += 1;
+                }
+            }
+        });
+        Search {
+            command,
+            hits: vec![],
+            lines: vec![],
+            regex,
+            results,
+            cancel,
+            done: false,
+        }
+    }
+
+    // picks up the hits that came in since last time, returns how many
+    pub fn poll(&mut self) -> usize {
+        let before = self.hits.len();
+        loop {
+            match self.results.try_recv() {
+                Ok((hit, line)) => {
+                    self.hits.push(hit);
+                    self.lines.push(line);
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.done = true;
+                    break;
+                }
+            }
+        }
+        self.hits.len() - before
+    }
+
+    pub fn done(&self) -> bool {
+        self.done
+    }
+
+    pub fn title(&self) -> String {
+        let status = match (self.done, self.hits.len()) {
+            (false, _) => "still looking".to_string(),
+            (true, LIMIT) => format!("stopped at {LIMIT}"),
+            (true, _) => "enter to jump to one".to_string(),
+        };
+        format!("{}: {} hits, {status}", self.command, self.hits.len())
+    }
+}
+
+impl Drop for Search {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
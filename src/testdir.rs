@@ -0,0 +1,37 @@
+// a directory of its own for each test, under the temp dir and gone once the
+// test is over, failed or not
+use std::{
+    env, fs,
+    ops::Deref,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+// tests run side by side, the pid keeps runs apart and this keeps tests apart
+static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+pub struct TestDir(PathBuf);
+
+impl TestDir {
+    pub fn new(name: &str) -> TestDir {
+        let count = COUNT.fetch_add(1, Ordering::Relaxed);
+        let path = env::temp_dir().join(format!("lga-test-{name}-{}-{count}", process::id()));
+        fs::create_dir(&path).unwrap();
+        TestDir(path)
+    }
+}
+
+impl Deref for TestDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
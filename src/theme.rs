@@ -0,0 +1,457 @@
+// how lga looks. one of the built in themes to start from, whatever the
+// [theme] part of the config changes on top, and the file colors from
+// LS_COLORS. NO_COLOR makes the monochrome one the default
+use crate::vfs;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{env, fs::Metadata, os::unix::fs::PermissionsExt, path::Path};
+use tui::style::{Color, Modifier, Style};
+
+// the colors tui knows by name, the rest are written as #rrggbb or a number
+// from the 256 color palette
+const COLORS: [(&str, Color); 17] = [
+    ("reset", Color::Reset),
+    ("black", Color::Black),
+    ("red", Color::Red),
+    ("green", Color::Green),
+    ("yellow", Color::Yellow),
+    ("blue", Color::Blue),
+    ("magenta", Color::Magenta),
+    ("cyan", Color::Cyan),
+    ("gray", Color::Gray),
+    ("darkgray", Color::DarkGray),
+    ("lightred", Color::LightRed),
+    ("lightgreen", Color::LightGreen),
+    ("lightyellow", Color::LightYellow),
+    ("lightblue", Color::LightBlue),
+    ("lightmagenta", Color::LightMagenta),
+    ("lightcyan", Color::LightCyan),
+    ("white", Color::White),
+];
+
+const MODIFIERS: [(&str, Modifier); 7] = [
+    ("bold", Modifier::BOLD),
+    ("dim", Modifier::DIM),
+    ("italic", Modifier::ITALIC),
+    ("underlined", Modifier::UNDERLINED),
+    ("blink", Modifier::SLOW_BLINK),
+    ("reversed", Modifier::REVERSED),
+    ("crossed", Modifier::CROSSED_OUT),
+];
+
+fn parse_color(name: &str) -> Option<Color> {
+    let name = name.to_lowercase().replace(['-', '_'], "");
+    if let Some((_, color)) = COLORS.iter().find(|(n, _)| *n == name) {
+        return Some(*color);
+    }
+    match name.strip_prefix('#') {
+        Some(hex) if hex.len() == 6 => {
+            let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+            Some(Color::Rgb(channel(0)?, channel(2)?, channel(4)?))
+        }
+        Some(_) => None,
+        None => name.parse().ok().map(Color::Indexed),
+    }
+}
+
+fn color_name(color: Color) -> String {
+    match color {
+        Color::Rgb(r, g, b) => format!("#{r:02x}{g:02x}{b:02x}"),
+        Color::Indexed(i) => i.to_string(),
+        color => COLORS
+            .iter()
+            .find(|(_, c)| *c == color)
+            .map(|(name, _)| name.to_string())
+            .unwrap_or_default(),
+    }
+}
+
+// a style written out, like `bold lightblue` or `black on green`. the color
+// after `on` goes in the back
+pub fn parse_style(spec: &str) -> Result<Style, String> {
+    let mut style = Style::default();
+    let mut words = spec.split_whitespace();
+    while let Some(word) = words.next() {
+        let lowercase = word.to_lowercase();
+        if let Some((_, modifier)) = MODIFIERS.iter().find(|(n, _)| *n == lowercase) {
+            style = style.add_modifier(*modifier);
+        } else if lowercase == "on" {
+            let back = words.next().ok_or("`on` needs a color after it")?;
+            style = style.bg(parse_color(back).ok_or(format!("`{back}` is not a color"))?);
+        } else if lowercase != "plain" {
+            style = style.fg(parse_color(word).ok_or(format!("`{word}` is not a color"))?);
+        }
+    }
+    Ok(style)
+}
+
+fn style_name(style: Style) -> String {
+    let mut words: Vec<String> = MODIFIERS
+        .iter()
+        .filter(|(_, modifier)| style.add_modifier.contains(*modifier))
+        .map(|(name, _)| name.to_string())
+        .collect();
+    if let Some(fg) = style.fg {
+        words.push(color_name(fg));
+    }
+    if let Some(bg) = style.bg {
+        words.push(format!("on {}", color_name(bg)));
+    }
+    match words.is_empty() {
+        true => "plain".to_string(),
+        false => words.join(" "),
+    }
+}
+
+// a style in the config, written like parse_style reads it
+#[derive(Clone, Copy)]
+pub struct Look(pub Style);
+
+impl Serialize for Look {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&style_name(self.0))
+    }
+}
+
+impl<'de> Deserialize<'de> for Look {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let spec = String::deserialize(deserializer)?;
+        parse_style(&spec).map(Look).map_err(|e| {
+            de::Error::custom(format!(
+                "{e}, colors are names like lightblue, #rrggbb or 0 to 255 and \
+                 bold, dim, italic, underlined, blink, reversed or crossed go with them"
+            ))
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Base {
+    Default,
+    // for terminals with a light background
+    Light,
+    // no colors at all, what NO_COLOR gets
+    Mono,
+}
+
+// the [theme] part of the config, only what is set there is different from
+// the base
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    // default unless NO_COLOR is set, then mono
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base: Option<Base>,
+    // whether LS_COLORS gets a say in how files look
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ls_colors: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub header: Option<Look>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub footer: Option<Look>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent: Option<Look>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current: Option<Look>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub listing: Option<Look>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preview: Option<Look>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub directory: Option<Look>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<Look>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symlink: Option<Look>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub executable: Option<Look>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<Look>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tagged: Option<Look>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selected: Option<Look>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dim: Option<Look>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matched: Option<Look>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub found: Option<Look>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub panel: Option<Look>,
+}
+
+pub struct Theme {
+    // the path on top
+    pub header: Style,
+    // the message and whatever is on the right of it at the bottom
+    pub footer: Style,
+    // the columns, the middle one is the current directory and the right one
+    // shows what it lists or the preview
+    pub parent: Style,
+    pub current: Style,
+    pub listing: Style,
+    pub preview: Style,
+    // the names in the middle column, going by what they are
+    pub directory: Style,
+    pub file: Style,
+    pub symlink: Style,
+    pub executable: Style,
+    // the highlighted line wherever there is one
+    pub cursor: Style,
+    // these go on top of how the name already looks
+    pub tagged: Style,
+    pub selected: Style,
+    // line numbers and loading
+    pub dim: Style,
+    // what :grep found in the preview
+    pub matched: Style,
+    // the letters :find matched
+    pub found: Style,
+    // the lists that pop up over everything
+    pub panel: Style,
+    // file name endings from LS_COLORS, like .tar or ~
+    endings: Vec<(String, Style)>,
+    // no colors from anywhere, previews dont get highlighted either
+    pub plain: bool,
+}
+
+fn style(spec: &str) -> Style {
+    parse_style(spec).unwrap_or_default()
+}
+
+impl Theme {
+    fn base(base: Base) -> Theme {
+        let [header, footer, parent, current, listing, preview, directory, file, symlink, executable, cursor, tagged, selected, dim, matched, found, panel] =
+            match base {
+                Base::Default => [
+                    "magenta",
+                    "plain",
+                    "bold lightblue",
+                    "bold white",
+                    "bold lightred",
+                    "gray",
+                    "green",
+                    "gray",
+                    "cyan",
+                    "yellow",
+                    "bold black on green",
+                    "lightmagenta",
+                    "underlined",
+                    "darkgray",
+                    "black on yellow",
+                    "underlined yellow",
+                    "white",
+                ],
+                Base::Light => [
+                    "magenta",
+                    "plain",
+                    "bold blue",
+                    "bold black",
+                    "bold red",
+                    "reset",
+                    "blue",
+                    "black",
+                    "cyan",
+                    "green",
+                    "bold white on blue",
+                    "magenta",
+                    "underlined",
+                    "gray",
+                    "black on lightyellow",
+                    "underlined red",
+                    "black",
+                ],
+                Base::Mono => [
+                    "bold",
+                    "plain",
+                    "plain",
+                    "bold",
+                    "plain",
+                    "plain",
+                    "bold",
+                    "plain",
+                    "italic",
+                    "plain",
+                    "reversed",
+                    "underlined",
+                    "underlined",
+                    "dim",
+                    "reversed",
+                    "underlined",
+                    "plain",
+                ],
+            };
+        Theme {
+            header: style(header),
+            footer: style(footer),
+            parent: style(parent),
+            current: style(current),
+            listing: style(listing),
+            preview: style(preview),
+            directory: style(directory),
+            file: style(file),
+            symlink: style(symlink),
+            executable: style(executable),
+            cursor: style(cursor),
+            tagged: style(tagged),
+            selected: style(selected),
+            dim: style(dim),
+            matched: style(matched),
+            found: style(found),
+            panel: style(panel),
+            endings: vec![],
+            plain: base == Base::Mono,
+        }
+    }
+
+    pub fn new(config: &ThemeConfig) -> Theme {
+        let no_color = env::var("NO_COLOR").is_ok_and(|v| !v.is_empty());
+        let base = match (config.base, no_color) {
+            (Some(base), _) => base,
+            (None, true) => Base::Mono,
+            (None, false) => Base::Default,
+        };
+        let mut theme = Theme::base(base);
+        // the config has the last word, so LS_COLORS goes first
+        if config.ls_colors.unwrap_or(true) && !theme.plain {
+            if let Ok(ls_colors) = env::var("LS_COLORS") {
+                theme.ls_colors(&ls_colors);
+            }
+        }
+        let overrides = [
+            (&mut theme.header, config.header),
+            (&mut theme.footer, config.footer),
+            (&mut theme.parent, config.parent),
+            (&mut theme.current, config.current),
+            (&mut theme.listing, config.listing),
+            (&mut theme.preview, config.preview),
+            (&mut theme.directory, config.directory),
+            (&mut theme.file, config.file),
+            (&mut theme.symlink, config.symlink),
+            (&mut theme.executable, config.executable),
+            (&mut theme.cursor, config.cursor),
+            (&mut theme.tagged, config.tagged),
+            (&mut theme.selected, config.selected),
+            (&mut theme.dim, config.dim),
+            (&mut theme.matched, config.matched),
+            (&mut theme.found, config.found),
+            (&mut theme.panel, config.panel),
+        ];
+        for (style, look) in overrides {
+            if let Some(Look(look)) = look {
+                *style = look;
+            }
+        }
+        theme
+    }
+
+    // di, ln, ex, fi and the *.ext entries, the rest (sockets, setuid and so
+    // on) lga doesnt tell apart
+    fn ls_colors(&mut self, ls_colors: &str) {
+        for entry in ls_colors.split(':') {
+            let (key, codes) = match entry.split_once('=') {
+                Some(entry) => entry,
+                None => continue,
+            };
+            let style = match sgr(codes) {
+                Some(style) => style,
+                None => continue,
+            };
+            match key {
+                "di" => self.directory = style,
+                "ln" => self.symlink = style,
+                "ex" => self.executable = style,
+                "fi" => self.file = style,
+                key => {
+                    if let Some(ending) = key.strip_prefix('*') {
+                        self.endings.push((ending.to_lowercase(), style));
+                    }
+                }
+            }
+        }
+        // the longest ending that fits wins, like .tar.gz over .gz
+        self.endings
+            .sort_by_key(|(ending, _)| usize::MAX - ending.len());
+    }
+
+    // what a name in the middle column looks like, going by what it is
+    // metadata is what the listing took, so nothing gets looked up per frame
+    pub fn entry(&self, path: &Path, metadata: Option<&Metadata>) -> Style {
+        let ending = || {
+            let name = path.file_name()?.to_string_lossy().to_lowercase();
+            self.endings
+                .iter()
+                .find(|(ending, _)| name.ends_with(ending.as_str()))
+                .map(|(_, style)| *style)
+        };
+        match metadata {
+            Some(metadata) if metadata.is_symlink() => self.symlink,
+            Some(metadata) if metadata.is_dir() => self.directory,
+            Some(metadata) if metadata.permissions().mode() & 0o111 != 0 => self.executable,
+            Some(_) => ending().unwrap_or(self.file),
+            // inside an archive
+            None if vfs::is_dir(path) => self.directory,
+            None => ending().unwrap_or(self.file),
+        }
+    }
+}
+
+// the 8 colors and their bright versions, in the order terminals number them
+const ANSI: [Color; 16] = [
+    Color::Black,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::Gray,
+    Color::DarkGray,
+    Color::LightRed,
+    Color::LightGreen,
+    Color::LightYellow,
+    Color::LightBlue,
+    Color::LightMagenta,
+    Color::LightCyan,
+    Color::White,
+];
+
+// the escape codes LS_COLORS is written in, like 01;34 or 38;5;208
+fn sgr(codes: &str) -> Option<Style> {
+    let codes: Vec<u8> = codes
+        .split(';')
+        .map(|code| match code {
+            "" => Some(0),
+            code => code.parse().ok(),
+        })
+        .collect::<Option<_>>()?;
+    let mut style = Style::default();
+    let mut codes = codes.into_iter();
+    // 38 and 48 take the color from the codes after them
+    let extended = |codes: &mut std::vec::IntoIter<u8>| match codes.next()? {
+        5 => Some(Color::Indexed(codes.next()?)),
+        2 => Some(Color::Rgb(codes.next()?, codes.next()?, codes.next()?)),
+        _ => None,
+    };
+    while let Some(code) = codes.next() {
+        style = match code {
+            0 => Style::default(),
+            1 => style.add_modifier(Modifier::BOLD),
+            2 => style.add_modifier(Modifier::DIM),
+            3 => style.add_modifier(Modifier::ITALIC),
+            4 => style.add_modifier(Modifier::UNDERLINED),
+            5 => style.add_modifier(Modifier::SLOW_BLINK),
+            7 => style.add_modifier(Modifier::REVERSED),
+            9 => style.add_modifier(Modifier::CROSSED_OUT),
+            30..=37 => style.fg(ANSI[code as usize - 30]),
+            90..=97 => style.fg(ANSI[code as usize - 90 + 8]),
+            40..=47 => style.bg(ANSI[code as usize - 40]),
+            100..=107 => style.bg(ANSI[code as usize - 100 + 8]),
+            38 => style.fg(extended(&mut codes)?),
+            48 => style.bg(extended(&mut codes)?),
+            _ => style,
+        };
+    }
+    Some(style)
+}
@@ -0,0 +1,390 @@
+// moving things to the trash the way the freedesktop.org trash spec wants it
+// https://specifications.freedesktop.org/trash-spec/trashspec-latest.html
+use chrono::Local;
+use directories::BaseDirs;
+use std::{
+    fs::{self, DirBuilder, File, OpenOptions},
+    io::{self, Error, ErrorKind, Write},
+    os::unix::{
+        ffi::OsStrExt,
+        fs::{DirBuilderExt, MetadataExt, PermissionsExt},
+    },
+    path::{Path, PathBuf},
+};
+
+pub struct TrashEntry {
+    // the trash directory this lives in, the one holding files/ and info/
+    pub trash: PathBuf,
+    // name inside files/ and info/
+    pub name: String,
+    pub original: PathBuf,
+    pub deleted: String,
+}
+
+impl TrashEntry {
+    pub fn file(&self) -> PathBuf {
+        self.trash.join("files").join(&self.name)
+    }
+
+    fn info(&self) -> PathBuf {
+        self.trash
+            .join("info")
+            .join(format!("{}.trashinfo", self.name))
+    }
+}
+
+fn uid() -> u32 {
+    unsafe { libc::getuid() }
+}
+
+fn home_trash() -> io::Result<PathBuf> {
+    match BaseDirs::new() {
+        Some(dirs) => Ok(dirs.data_dir().join("Trash")),
+        None => Err(Error::new(ErrorKind::NotFound, "no home directory")),
+    }
+}
+
+// the mount point the path lives on, found by walking up until the device
+// changes
+fn topdir(path: &Path) -> io::Result<PathBuf> {
+    let dev = path.symlink_metadata()?.dev();
+    let mut top = path.to_path_buf();
+    while let Some(parent) = top.parent() {
+        if parent.metadata()?.dev() != dev {
+            break;
+        }
+        top = parent.to_path_buf();
+    }
+    Ok(top)
+}
+
+// $topdir/.Trash/$uid if the admin made one that is safe to use, otherwise
+// $topdir/.Trash-$uid
+fn topdir_trash(top: &Path) -> io::Result<PathBuf> {
+    let shared = top.join(".Trash");
+    if let Ok(metadata) = shared.symlink_metadata() {
+        let sticky = metadata.permissions().mode() & 0o1000 != 0;
+        if metadata.is_dir() && sticky {
+            let trash = shared.join(uid().to_string());
+            if DirBuilder::new()
+                .recursive(true)
+                .mode(0o700)
+                .create(&trash)
+                .is_ok()
+            {
+                return Ok(trash);
+            }
+        }
+    }
+    let trash = top.join(format!(".Trash-{}", uid()));
+    DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(&trash)?;
+    Ok(trash)
+}
+
+// picks the trash directory for the path and whether paths in its trashinfo
+// files are relative to the mount point
+fn trash_for(path: &Path) -> io::Result<(PathBuf, Option<PathBuf>)> {
+    let home = home_trash()?;
+    DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(&home)?;
+    if home.metadata()?.dev() == path.symlink_metadata()?.dev() {
+        return Ok((home, None));
+    }
+    let top = topdir(path)?;
+    Ok((topdir_trash(&top)?, Some(top)))
+}
+
+fn encode(path: &Path) -> String {
+    let mut encoded = String::new();
+    for &b in path.as_os_str().as_bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                encoded.push(b as char)
+            }
+            _ => encoded.push_str(&format!("%{b:02X}")),
+        }
+    }
+    encoded
+}
+
+fn decode(s: &str) -> PathBuf {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(b)) => {
+                decoded.push(b);
+                i += 3;
+            }
+            (b, _) => {
+                decoded.push(b);
+                i += 1;
+            }
+        }
+    }
+    PathBuf::from(std::ffi::OsStr::from_bytes(&decoded))
+}
+
+// reserves a name in info/ by creating the trashinfo file, the spec wants that
+// to happen atomically so two programs cant pick the same name
+fn reserve(trash: &Path, file_name: &str) -> io::Result<(String, File)> {
+    let info = trash.join("info");
+    for n in 1.. {
+        let name = match n {
+            1 => file_name.to_string(),
+            n => format!("{file_name}.{n}"),
+        };
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(info.join(format!("{name}.trashinfo")))
+        {
+            Ok(file) => return Ok((name, file)),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    unreachable!()
+}
+
+pub fn trash(path: &Path) -> io::Result<TrashEntry> {
+    let path = match path.parent() {
+        Some(parent) => parent
+            .canonicalize()?
+            .join(path.file_name().unwrap_or_default()),
+        None => return Err(Error::new(ErrorKind::InvalidInput, "cant trash /")),
+    };
+    let (trash, top) = trash_for(&path)?;
+    trash_in(&path, trash, top.as_deref())
+}
+
+// moves the absolute path into that trash directory. the trashinfo has the
+// path relative to top when theres one
+pub fn trash_in(path: &Path, trash: PathBuf, top: Option<&Path>) -> io::Result<TrashEntry> {
+    fs::create_dir_all(trash.join("files"))?;
+    fs::create_dir_all(trash.join("info"))?;
+    let file_name = path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let (name, mut info) = reserve(&trash, &file_name)?;
+    let recorded = match top {
+        Some(top) => path.strip_prefix(top).unwrap_or(path),
+        None => path,
+    };
+    let deleted = Local::now().format("%Y-%m-%dT%H:%M:%S").to_string();
+    let entry = TrashEntry {
+        trash,
+        name,
+        original: path.to_path_buf(),
+        deleted,
+    };
+    let written = write!(
+        info,
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        encode(recorded),
+        entry.deleted
+    );
+    match written.and_then(|_| fs::rename(path, entry.file())) {
+        Ok(_) => Ok(entry),
+        Err(e) => {
+            let _ = fs::remove_file(entry.info());
+            Err(e)
+        }
+    }
+}
+
+// the home trash and the trash directories of every mounted filesystem
+fn trash_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![];
+    if let Ok(home) = home_trash() {
+        dirs.push(home);
+    }
+    if let Ok(mounts) = fs::read_to_string("/proc/self/mounts") {
+        for mount in mounts.lines() {
+            if let Some(top) = mount.split(' ').nth(1) {
+                // spaces and friends are escaped as octal in there
+                let top = PathBuf::from(top.replace("\\040", " "));
+                dirs.push(top.join(".Trash").join(uid().to_string()));
+                dirs.push(top.join(format!(".Trash-{}", uid())));
+            }
+        }
+    }
+    dirs.retain(|dir| dir.join("info").is_dir());
+    // the same mount can show up more than once
+    dirs.sort();
+    dirs.dedup();
+    dirs
+}
+
+fn read_info(trash: &Path, info: &Path) -> Option<TrashEntry> {
+    let name = info.file_stem()?.to_string_lossy().to_string();
+    let content = fs::read_to_string(info).ok()?;
+    let mut original = None;
+    let mut deleted = String::new();
+    for line in content.lines() {
+        match line.split_once('=') {
+            Some(("Path", path)) => original = Some(decode(path)),
+            Some(("DeletionDate", date)) => deleted = date.to_string(),
+            _ => {}
+        }
+    }
+    let original = original?;
+    let original = if original.is_relative() {
+        // relative paths only show up in topdir trashes, which live right
+        // under the mount point (or one further down for .Trash/$uid)
+        let top = match trash.file_name()?.to_str()?.starts_with(".Trash-") {
+            true => trash.parent()?,
+            false => trash.parent()?.parent()?,
+        };
+        top.join(original)
+    } else {
+        original
+    };
+    Some(TrashEntry {
+        trash: trash.to_path_buf(),
+        name,
+        original,
+        deleted,
+    })
+}
+
+// everything in every trash, most recently deleted first
+pub fn list() -> Vec<TrashEntry> {
+    let mut entries: Vec<TrashEntry> = trash_dirs()
+        .iter()
+        .flat_map(|trash| {
+            fs::read_dir(trash.join("info"))
+                .into_iter()
+                .flatten()
+                .flatten()
+                .filter_map(|info| read_info(trash, &info.path()))
+                .collect::<Vec<TrashEntry>>()
+        })
+        .filter(|entry| entry.file().symlink_metadata().is_ok())
+        .collect();
+    entries.sort_by(|a, b| b.deleted.cmp(&a.deleted));
+    entries
+}
+
+pub fn restore(entry: &TrashEntry) -> io::Result<()> {
+    if entry.original.symlink_metadata().is_ok() {
+        return Err(Error::new(
+            ErrorKind::AlreadyExists,
+            format!("{} already exists", entry.original.display()),
+        ));
+    }
+    if let Some(parent) = entry.original.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::rename(entry.file(), &entry.original)?;
+    fs::remove_file(entry.info())
+}
+
+fn remove(path: &Path) -> io::Result<()> {
+    match path.symlink_metadata()?.is_dir() {
+        true => fs::remove_dir_all(path),
+        false => fs::remove_file(path),
+    }
+}
+
+fn erase(entry: &TrashEntry) -> io::Result<()> {
+    remove(&entry.file())?;
+    fs::remove_file(entry.info())
+}
+
+// permanently deletes everything in every trash, returns how many items went
+pub fn empty() -> io::Result<usize> {
+    let mut count = 0;
+    for entry in list() {
+        erase(&entry)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testdir::TestDir;
+    use std::ffi::OsStr;
+
+    #[test]
+    fn paths_are_percent_encoded() {
+        let path = Path::new("/home/me/a file%20with ü.txt");
+        assert_eq!(encode(path), "/home/me/a%20file%2520with%20%C3%BC.txt");
+        assert_eq!(decode(&encode(path)), path);
+        // bytes that arent utf-8 make it through too
+        let path = Path::new(OsStr::from_bytes(b"/tmp/\xff\n"));
+        assert_eq!(encode(path), "/tmp/%FF%0A");
+        assert_eq!(decode(&encode(path)), path);
+        // a stray % is left the way it is
+        assert_eq!(decode("/100%/%zz%4"), Path::new("/100%/%zz%4"));
+    }
+
+    #[test]
+    fn names_dont_collide() {
+        let trash = TestDir::new("trash-reserve");
+        fs::create_dir(trash.join("info")).unwrap();
+        let names: Vec<String> = (0..3).map(|_| reserve(&trash, "a").unwrap().0).collect();
+        assert_eq!(names, ["a", "a.2", "a.3"]);
+        assert!(trash.join("info").join("a.3.trashinfo").is_file());
+    }
+
+    #[test]
+    fn restoring_puts_it_back() {
+        let dir = TestDir::new("trash-restore");
+        let trash = dir.join("trash");
+        let path = dir.join("sub").join("a b");
+        fs::create_dir(dir.join("sub")).unwrap();
+        fs::write(&path, "a").unwrap();
+        let entry = trash_in(&path, trash.to_path_buf(), None).unwrap();
+        assert!(!path.exists());
+        assert_eq!(fs::read_to_string(entry.file()).unwrap(), "a");
+        let info = fs::read_to_string(entry.info()).unwrap();
+        assert!(info.starts_with(&format!(
+            "[Trash Info]\nPath={}\nDeletionDate=",
+            encode(&path)
+        )));
+        // the way it gets read back in for the trash view
+        let read = read_info(&trash, &entry.info()).unwrap();
+        assert_eq!(read.name, "a b");
+        assert_eq!(read.original, path);
+        assert_eq!(read.deleted, entry.deleted);
+        // the directory it was in is made again if its gone
+        fs::remove_dir(dir.join("sub")).unwrap();
+        restore(&read).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "a");
+        assert!(!entry.file().exists() && !entry.info().exists());
+        // and nothing gets restored over something new
+        let entry = trash_in(&path, trash.to_path_buf(), None).unwrap();
+        fs::write(&path, "new").unwrap();
+        let error = restore(&entry).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::AlreadyExists);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert!(entry.file().exists());
+    }
+
+    #[test]
+    fn topdir_trashes_have_relative_paths() {
+        let top = TestDir::new("trash-topdir");
+        let trash = top.join(format!(".Trash-{}", uid()));
+        let path = top.join("a");
+        fs::write(&path, "a").unwrap();
+        let entry = trash_in(&path, trash.to_path_buf(), Some(&top)).unwrap();
+        let info = fs::read_to_string(entry.info()).unwrap();
+        assert!(info.contains("\nPath=a\n"));
+        assert_eq!(read_info(&trash, &entry.info()).unwrap().original, path);
+    }
+}
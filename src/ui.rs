@@ -1,36 +1,179 @@
-use crate::App;
+use crate::{
+    details::{self, Detail},
+    App, InputMode,
+};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
 use tui::{
     backend::Backend,
-    layout::{Alignment, Constraint, Direction, Layout},
-    style::{Color, Modifier, Style},
-    widgets::{Block, BorderType, Borders, List, ListItem, Paragraph},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::Style,
+    text::{Span, Spans, Text},
+    widgets::{Block, BorderType, Borders, Clear, List, ListItem, Paragraph, Wrap},
     Frame,
 };
 
+// which of the columns show up, the middle one always does
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Panes {
+    All,
+    NoParent,
+    NoPreview,
+    Single,
+}
+
+impl Panes {
+    fn new(parent: bool, preview: bool) -> Panes {
+        match (parent, preview) {
+            (true, true) => Panes::All,
+            (false, true) => Panes::NoParent,
+            (true, false) => Panes::NoPreview,
+            (false, false) => Panes::Single,
+        }
+    }
+
+    pub fn parent(&self) -> bool {
+        matches!(self, Panes::All | Panes::NoPreview)
+    }
+
+    pub fn preview(&self) -> bool {
+        matches!(self, Panes::All | Panes::NoParent)
+    }
+
+    pub fn toggle_parent(&self) -> Panes {
+        Panes::new(!self.parent(), self.preview())
+    }
+
+    pub fn toggle_preview(&self) -> Panes {
+        Panes::new(self.parent(), !self.preview())
+    }
+}
+
+// the preview with a gutter of line numbers
+fn numbered(preview: &Text<'static>, dim: Style) -> Text<'static> {
+    let width = preview.height().to_string().len();
+    let lines: Vec<Spans> = preview
+        .lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            let mut spans = vec![Span::styled(format!("{:>width$} ", i + 1), dim)];
+            spans.extend(line.0.iter().cloned());
+            Spans::from(spans)
+        })
+        .collect();
+    Text::from(lines)
+}
+
+// the grep match on that line stands out
+fn marked(preview: &Text<'static>, line: usize, regex: &Regex, matched: Style) -> Text<'static> {
+    let mut preview = preview.clone();
+    let spans = match line.checked_sub(1).and_then(|i| preview.lines.get_mut(i)) {
+        Some(spans) => spans,
+        None => return preview,
+    };
+    let text: String = spans.0.iter().map(|span| span.content.as_ref()).collect();
+    let matches: Vec<Range<usize>> = regex
+        .find_iter(&text)
+        .map(|m| m.range())
+        .filter(|range| !range.is_empty())
+        .collect();
+    // the highlighted spans get cut where the matches start and end
+    let mut split = vec![];
+    let mut offset = 0;
+    for span in &spans.0 {
+        let end = offset + span.content.len();
+        let mut cuts: Vec<usize> = matches
+            .iter()
+            .flat_map(|range| [range.start, range.end])
+            .filter(|&cut| cut > offset && cut < end)
+            .collect();
+        cuts.push(end);
+        let mut start = offset;
+        for cut in cuts {
+            let style = match matches.iter().any(|range| range.contains(&start)) {
+                true => span.style.patch(matched),
+                false => span.style,
+            };
+            split.push(Span::styled(
+                span.content[start - offset..cut - offset].to_string(),
+                style,
+            ));
+            start = cut;
+        }
+        offset = end;
+    }
+    *spans = Spans::from(split);
+    preview
+}
+
+// a rect in the middle of the area, sizes in percent
+fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Percentage((100 - height) / 2),
+                Constraint::Percentage(height),
+                Constraint::Percentage((100 - height) / 2),
+            ]
+            .as_ref(),
+        )
+        .split(area);
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(
+            [
+                Constraint::Percentage((100 - width) / 2),
+                Constraint::Percentage(width),
+                Constraint::Percentage((100 - width) / 2),
+            ]
+            .as_ref(),
+        )
+        .split(vertical[1])[1]
+}
+
 pub fn ui<B: Backend>(frame: &mut Frame<B>, app: &mut App) {
-    // Chunks
+    let theme = &app.theme;
+    // Chunks, a row each for the header and the footer
     let vertical_chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints(
             [
-                Constraint::Percentage(3),
-                Constraint::Percentage(96),
-                Constraint::Percentage(1),
+                Constraint::Length(1),
+                Constraint::Min(0),
+                Constraint::Length(1),
             ]
             .as_ref(),
         )
         .split(frame.size());
+    // the columns that show up as parts of what they add up to
+    let shown = [app.panes.parent(), true, app.panes.preview()];
+    let ratios: Vec<u32> = (0..3)
+        .filter(|&i| shown[i])
+        .map(|i| app.columns[i] as u32)
+        .collect();
+    let total = ratios.iter().sum::<u32>().max(1);
     let chunks = Layout::default()
         .direction(Direction::Horizontal)
         .constraints(
-            [
-                Constraint::Percentage(20),
-                Constraint::Percentage(40),
-                Constraint::Percentage(40),
-            ]
-            .as_ref(),
+            ratios
+                .iter()
+                .map(|&n| Constraint::Ratio(n, total))
+                .collect::<Vec<_>>(),
         )
         .split(vertical_chunks[1]);
+    let mut chunks = chunks.iter().copied();
+    let parent_chunk = match shown[0] {
+        true => chunks.next(),
+        false => None,
+    };
+    let middle_chunk = chunks.next().unwrap_or_default();
+    let preview_chunk = chunks.next();
+    // minus the borders
+    app.preview_height = preview_chunk.map_or(0, |chunk| chunk.height.saturating_sub(2));
     // Create a block...
     let left_column_list: Vec<ListItem> = app
         .left_column
@@ -39,37 +182,68 @@ pub fn ui<B: Backend>(frame: &mut Frame<B>, app: &mut App) {
         .map(|item| ListItem::new(item.path.file_name().unwrap().to_str().unwrap()))
         .collect();
 
+    // the long view lines the details up after the names, as many of them as
+    // fit in the borders next to the widest names. only the rows that can be
+    // on screen get them, a height either way of the cursor
+    let columns = &app.config.details.columns;
+    let height = middle_chunk.height.saturating_sub(2) as usize;
+    let cursor = app.middle_column.state.selected().unwrap_or(0);
+    let first = (cursor + 1).saturating_sub(height);
+    let rows: Vec<Vec<String>> = match app.details {
+        true => app
+            .middle_column
+            .items
+            .iter()
+            .skip(first)
+            .take((cursor + height).saturating_sub(first))
+            .map(|item| app.names.cells(&item.path, item.metadata.as_ref(), columns))
+            .collect(),
+        false => vec![],
+    };
+    let inside = middle_chunk.width.saturating_sub(2) as usize;
+    let widths = details::fit(&rows, inside);
+    let used: usize = widths.iter().map(|&w| w + (w > 0) as usize).sum();
     let middle_column_list: Vec<ListItem> = app
         .middle_column
         .items
         .iter()
-        .map(|item| {
+        .enumerate()
+        .map(|(i, item)| {
             let tagged = if item.tagged { '*' } else { ' ' };
+            let mut style = theme.entry(&item.path, item.metadata.as_ref());
+            if item.tagged {
+                style = style.patch(theme.tagged);
+            }
             let item = &item.path;
             let selected = match &app.input_mode {
-                crate::InputMode::Select(v) => {
-                    if v.contains(item) {
-                        " "
-                    } else {
-                        ""
-                    }
+                InputMode::Select(v) if v.contains(item) => {
+                    style = style.patch(theme.selected);
+                    " "
                 }
                 _ => "",
             };
             // deal with those unwraps man
-            if item.is_dir() {
-                ListItem::new(format!(
-                    "{tagged}{selected}{}",
-                    item.file_name().unwrap().to_str().unwrap(),
-                ))
-                .style(Style::default().fg(Color::Green))
-            } else {
-                ListItem::new(format!(
-                    "{tagged}{selected}{}",
-                    item.file_name().unwrap().to_str().unwrap()
-                ))
-                .style(Style::default().fg(Color::Gray))
+            let name = format!(
+                "{tagged}{selected}{}",
+                item.file_name().unwrap().to_str().unwrap(),
+            );
+            let row = match i.checked_sub(first).and_then(|i| rows.get(i)) {
+                Some(row) => row,
+                None => return ListItem::new(name).style(style),
+            };
+            let name = details::pad(&name, inside.saturating_sub(used));
+            let mut spans = vec![Span::styled(name, style)];
+            for ((cell, &width), detail) in row.iter().zip(&widths).zip(columns) {
+                if width == 0 {
+                    continue;
+                }
+                let cell = match detail {
+                    Detail::Size => format!(" {cell:>width$}"),
+                    _ => format!(" {cell:<width$}"),
+                };
+                spans.push(Span::styled(cell, theme.dim));
             }
+            ListItem::new(Spans::from(spans)).style(style)
         })
         .collect();
 
@@ -86,11 +260,7 @@ pub fn ui<B: Backend>(frame: &mut Frame<B>, app: &mut App) {
                 .borders(Borders::ALL)
                 .border_type(BorderType::Rounded),
         )
-        .style(
-            Style::default()
-                .fg(Color::LightBlue)
-                .add_modifier(Modifier::BOLD),
-        );
+        .style(theme.parent);
 
     let middle_block = List::new(middle_column_list)
         .block(
@@ -98,70 +268,165 @@ pub fn ui<B: Backend>(frame: &mut Frame<B>, app: &mut App) {
                 .borders(Borders::ALL)
                 .border_type(BorderType::Rounded),
         )
-        .style(
-            Style::default()
-                .fg(Color::White)
-                .add_modifier(Modifier::BOLD),
-        )
-        .highlight_style(
-            Style::default()
-                .bg(Color::Green)
-                .fg(Color::Black)
-                .add_modifier(Modifier::BOLD),
-        );
+        .style(theme.current)
+        .highlight_style(theme.cursor);
 
     // header
-    let header = match app.get_selected() {
+    let mut header = match app.get_selected() {
         Some(selected) => selected.path.display().to_string(),
         None => app.pwd.display().to_string(),
     };
+    if let Some((dir, pattern, _)) = &app.filter {
+        if *dir == app.pwd {
+            header.push_str(&format!("  [filter: {pattern}]"));
+        }
+    }
     let header = Paragraph::new(header)
-        .style(Style::default().fg(Color::Magenta))
+        .style(theme.header)
         .alignment(Alignment::Left);
 
     // footer(s)
-    let metadata = Paragraph::new(app.metadata.as_ref()).alignment(Alignment::Right);
-    let message = Paragraph::new(app.message.as_ref()).alignment(Alignment::Left);
-
+    let metadata = match app.jobs.is_empty() {
+        true => app.metadata.to_string(),
+        false => format!("{}  {}", app.jobs.summary(), app.metadata),
+    };
+    let metadata = Paragraph::new(metadata)
+        .style(theme.footer)
+        .alignment(Alignment::Right);
+    let message = Paragraph::new(app.message.as_ref())
+        .style(theme.footer)
+        .alignment(Alignment::Left);
 
     // Render into chunks of the layout.
     frame.render_widget(header, vertical_chunks[0]);
-    frame.render_widget(left_block, chunks[0]);
-    frame.render_stateful_widget(middle_block, chunks[1], &mut app.middle_column.state);
-    match app.get_selected() {
-        Some(selected) => match &selected.preview {
+    if let Some(chunk) = parent_chunk {
+        frame.render_widget(left_block, chunk);
+    }
+    frame.render_stateful_widget(middle_block, middle_chunk, &mut app.middle_column.state);
+    match (preview_chunk, app.get_selected()) {
+        (Some(chunk), Some(_)) if app.loader.loading() => {
+            let loading = Paragraph::new("loading…")
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .border_type(BorderType::Rounded),
+                )
+                .style(theme.dim);
+            frame.render_widget(loading, chunk);
+        }
+        (Some(chunk), Some(selected)) => match &selected.preview {
             Some(preview) => {
-                let prev = Paragraph::new(preview.to_owned())
+                let preview = match &app.mark {
+                    Some((path, line, regex)) if *path == selected.path => {
+                        marked(preview, *line, regex, theme.matched)
+                    }
+                    _ => preview.clone(),
+                };
+                let preview = match app.line_numbers {
+                    true => numbered(&preview, theme.dim),
+                    false => preview,
+                };
+                // highlighted lines bring their own colors
+                let mut prev = Paragraph::new(preview)
                     .block(
                         Block::default()
                             .borders(Borders::ALL)
                             .border_type(BorderType::Rounded),
                     )
-                    .style(
-                        Style::default()
-                            .fg(Color::LightRed)
-                            .add_modifier(Modifier::BOLD),
-                    );
-                frame.render_widget(prev, chunks[2]);
+                    .style(theme.preview)
+                    .scroll((app.scroll, 0));
+                if app.wrap {
+                    prev = prev.wrap(Wrap { trim: false });
+                }
+                frame.render_widget(prev, chunk);
             }
             None => {
                 let right_block = List::new(right_column_list)
                     .block(
                         Block::default()
+                            .title(app.summary.as_str())
                             .borders(Borders::ALL)
                             .border_type(BorderType::Rounded),
                     )
-                    .style(
-                        Style::default()
-                            .fg(Color::LightRed)
-                            .add_modifier(Modifier::BOLD),
-                    );
-                frame.render_widget(right_block, chunks[2]);
+                    .style(theme.listing);
+                frame.render_widget(right_block, chunk);
             }
         },
-        None => {}
+        _ => {}
     };
     // frame.render_widget(right_block, chunks[2]);
     frame.render_widget(metadata, vertical_chunks[2]);
     frame.render_widget(message, vertical_chunks[2]);
+
+    // the ranked :find candidates over the middle column, the letters that
+    // matched stand out
+    if let InputMode::Input(input) = &app.input_mode {
+        if input.starts_with(":find ") {
+            let candidates: Vec<ListItem> = app
+                .finder
+                .items
+                .iter()
+                .map(|(path, m)| {
+                    let name = path.file_name().unwrap_or_default().to_string_lossy();
+                    // candidates are ranked again whenever the listing changes,
+                    // so the index still points at the item they came from
+                    let metadata = app
+                        .middle_column
+                        .items
+                        .get(m.index)
+                        .filter(|item| item.path == *path)
+                        .and_then(|item| item.metadata.as_ref());
+                    let style = theme.entry(path, metadata);
+                    let matched = style.patch(theme.found);
+                    let spans: Vec<Span> = name
+                        .chars()
+                        .enumerate()
+                        .map(|(i, c)| match m.positions.contains(&i) {
+                            true => Span::styled(c.to_string(), matched),
+                            false => Span::styled(c.to_string(), style),
+                        })
+                        .collect();
+                    ListItem::new(Spans::from(spans))
+                })
+                .collect();
+            let title = format!(
+                "find: {} of {}, tab/up/down to pick",
+                candidates.len(),
+                app.middle_column.items.len()
+            );
+            let finder = List::new(candidates)
+                .block(
+                    Block::default()
+                        .title(title)
+                        .borders(Borders::ALL)
+                        .border_type(BorderType::Rounded),
+                )
+                .style(theme.current)
+                .highlight_style(theme.cursor);
+            frame.render_widget(Clear, middle_chunk);
+            frame.render_stateful_widget(finder, middle_chunk, &mut app.finder.state);
+        }
+    }
+
+    // panels go on top of everything else
+    if let InputMode::Panel(panel) = &mut app.input_mode {
+        let area = centered(vertical_chunks[1], 80, 60);
+        let items: Vec<ListItem> = panel
+            .list
+            .items
+            .iter()
+            .map(|item| ListItem::new(item.as_str()))
+            .collect();
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .title(panel.title.as_str())
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded),
+            )
+            .style(theme.panel)
+            .highlight_style(theme.cursor);
+        frame.render_widget(Clear, area);
+        frame.render_stateful_widget(list, area, &mut panel.list.state);
+    }
 }
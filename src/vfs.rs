@@ -0,0 +1,626 @@
+// browsing archives like directories. a path like `stuff.tar.gz/dir/file`
+// points into the archive `stuff.tar.gz`, listings come from an index of the
+// archive and members get extracted when something needs the real file, like
+// the previews or an opener
+use crate::copy::{Progress, Report};
+use file_format::FileFormat;
+use flate2::read::GzDecoder;
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    env,
+    fs::{self, DirBuilder, File, OpenOptions},
+    hash::{Hash, Hasher},
+    io::{self, Error, ErrorKind, Read, Write},
+    os::unix::fs::{symlink, DirBuilderExt, MetadataExt, OpenOptionsExt, PermissionsExt},
+    path::{Component, Path, PathBuf},
+    process,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, LazyLock, Mutex,
+    },
+    time::SystemTime,
+};
+use xz2::read::XzDecoder;
+
+const CHUNK: usize = 1 << 20;
+
+struct Member {
+    dir: bool,
+    size: u64,
+}
+
+// what is in an archive, paths are relative to the archive with "" as the root
+struct Index {
+    modified: Option<SystemTime>,
+    members: HashMap<PathBuf, Member>,
+    children: HashMap<PathBuf, Vec<PathBuf>>,
+}
+
+// indexes are kept until the archive changes, tars have to be read all the
+// way through to list them
+static INDEXES: LazyLock<Mutex<HashMap<PathBuf, Arc<Index>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+// relative and without any way out of the archive, None for names that try
+pub fn clean(name: &Path) -> Option<PathBuf> {
+    let mut cleaned = PathBuf::new();
+    for component in name.components() {
+        match component {
+            Component::Normal(part) => cleaned.push(part),
+            Component::RootDir | Component::CurDir => {}
+            Component::ParentDir | Component::Prefix(_) => return None,
+        }
+    }
+    (!cleaned.as_os_str().is_empty()).then_some(cleaned)
+}
+
+// the archive formats lga can read, compressed files only count when there is
+// a tar inside
+enum Format {
+    Zip,
+    Tar,
+    TarGz,
+    TarXz,
+}
+
+fn format(path: &Path) -> Option<Format> {
+    match FileFormat::from_file(path).ok()? {
+        FileFormat::Zip => Some(Format::Zip),
+        FileFormat::TapeArchive => Some(Format::Tar),
+        FileFormat::Gzip => Some(Format::TarGz),
+        FileFormat::Xz => Some(Format::TarXz),
+        _ => None,
+    }
+}
+
+fn tar(format: &Format, file: File) -> tar::Archive<Box<dyn Read>> {
+    let reader: Box<dyn Read> = match format {
+        Format::TarGz => Box::new(GzDecoder::new(file)),
+        Format::TarXz => Box::new(XzDecoder::new(file)),
+        _ => Box::new(file),
+    };
+    tar::Archive::new(reader)
+}
+
+fn load(path: &Path) -> io::Result<Index> {
+    let format = format(path).ok_or(Error::other("not an archive"))?;
+    let file = File::open(path)?;
+    let mut entries = vec![];
+    match format {
+        Format::Zip => {
+            let mut zip = zip::ZipArchive::new(file).map_err(Error::other)?;
+            for i in 0..zip.len() {
+                let entry = zip.by_index_raw(i).map_err(Error::other)?;
+                entries.push((PathBuf::from(entry.name()), entry.is_dir(), entry.size()));
+            }
+        }
+        _ => {
+            for entry in tar(&format, file).entries()? {
+                let entry = entry?;
+                let dir = entry.header().entry_type().is_dir();
+                entries.push((entry.path()?.to_path_buf(), dir, entry.size()));
+            }
+        }
+    }
+    let mut members = HashMap::new();
+    members.insert(PathBuf::new(), Member { dir: true, size: 0 });
+    for (name, dir, size) in entries {
+        let name = match clean(&name) {
+            Some(name) => name,
+            None => continue,
+        };
+        // not every archive has entries for its directories
+        for parent in name.ancestors().skip(1) {
+            members
+                .entry(parent.to_path_buf())
+                .or_insert(Member { dir: true, size: 0 });
+        }
+        members.insert(name, Member { dir, size });
+    }
+    let mut children: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+    for name in members.keys() {
+        if let Some(parent) = name.parent() {
+            children
+                .entry(parent.to_path_buf())
+                .or_default()
+                .push(name.to_path_buf());
+        }
+    }
+    Ok(Index {
+        modified: path.metadata()?.modified().ok(),
+        members,
+        children,
+    })
+}
+
+fn index(archive: &Path) -> io::Result<Arc<Index>> {
+    let modified = archive.metadata()?.modified().ok();
+    let mut indexes = INDEXES.lock().unwrap();
+    match indexes.get(archive) {
+        Some(index) if index.modified == modified => Ok(Arc::clone(index)),
+        _ => {
+            let index = Arc::new(load(archive)?);
+            indexes.insert(archive.to_path_buf(), Arc::clone(&index));
+            Ok(index)
+        }
+    }
+}
+
+// a real file that can be browsed
+pub fn is_archive(path: &Path) -> bool {
+    path.is_file() && index(path).is_ok()
+}
+
+// the archive a path points into and the path inside it. anything that
+// exists for real, archives included, is None
+pub fn split(path: &Path) -> Option<(PathBuf, PathBuf)> {
+    if path.symlink_metadata().is_ok() {
+        return None;
+    }
+    for archive in path.ancestors().skip(1) {
+        match archive.metadata() {
+            Ok(metadata) if metadata.is_file() => {
+                let inner = path.strip_prefix(archive).ok()?;
+                return match index(archive).ok()?.members.contains_key(inner) {
+                    true => Some((archive.to_path_buf(), inner.to_path_buf())),
+                    false => None,
+                };
+            }
+            Ok(_) => return None,
+            Err(_) => continue,
+        }
+    }
+    None
+}
+
+// like split, but the archive itself is its own root
+fn locate(path: &Path) -> Option<(PathBuf, PathBuf)> {
+    match split(path) {
+        Some(split) => Some(split),
+        None if is_archive(path) => Some((path.to_path_buf(), PathBuf::new())),
+        None => None,
+    }
+}
+
+fn member<T>(path: &Path, f: impl Fn(&Member) -> T) -> Option<T> {
+    let (archive, inner) = split(path)?;
+    index(&archive).ok()?.members.get(&inner).map(f)
+}
+
+// real directories and directories inside archives, archives themselves are
+// still files
+pub fn is_dir(path: &Path) -> bool {
+    match path.metadata() {
+        Ok(metadata) => metadata.is_dir(),
+        Err(_) => member(path, |m| m.dir).unwrap_or(false),
+    }
+}
+
+pub fn len(path: &Path) -> Option<u64> {
+    match path.metadata() {
+        Ok(metadata) => Some(metadata.len()),
+        Err(_) => member(path, |m| m.size),
+    }
+}
+
+// members are as new as their archive
+pub fn modified(path: &Path) -> Option<SystemTime> {
+    match path.metadata() {
+        Ok(metadata) => metadata.modified().ok(),
+        Err(_) => split(path)?.0.metadata().ok()?.modified().ok(),
+    }
+}
+
+// whats in an archive or a directory inside one
+pub fn read_dir(path: &Path) -> io::Result<Vec<PathBuf>> {
+    let (archive, inner) = locate(path).ok_or(Error::from(ErrorKind::NotFound))?;
+    let index = index(&archive)?;
+    let mut paths: Vec<PathBuf> = index
+        .children
+        .get(&inner)
+        .into_iter()
+        .flatten()
+        .map(|child| archive.join(child))
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+// bytes and files under a path in an archive, or in all of it, like
+// copy::measure
+pub fn measure(path: &Path) -> (u64, usize) {
+    let (archive, inner) = match locate(path) {
+        Some(split) => split,
+        None => return (0, 0),
+    };
+    match index(&archive) {
+        Ok(index) => index
+            .members
+            .iter()
+            .filter(|(name, member)| name.starts_with(&inner) && !member.dir)
+            .fold((0, 0), |(bytes, files), (_, member)| {
+                (bytes + member.size, files + 1)
+            }),
+        Err(_) => (0, 0),
+    }
+}
+
+// never over something that is already there, and without setuid, setgid or
+// sticky bits, the archive could be from anyone
+fn write(
+    reader: &mut dyn Read,
+    target: &Path,
+    mode: Option<u32>,
+    report: Report,
+) -> io::Result<()> {
+    let mut writer = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(target)?;
+    let mut buffer = vec![0; CHUNK];
+    let written = (|| loop {
+        let n = reader.read(&mut buffer)?;
+        if n == 0 {
+            return Ok(());
+        }
+        writer.write_all(&buffer[..n])?;
+        report(Progress::Bytes(n as u64))?;
+    })();
+    if let Err(e) = written {
+        let _ = fs::remove_file(target);
+        return Err(e);
+    }
+    let mode = mode.unwrap_or(0o644);
+    fs::set_permissions(target, fs::Permissions::from_mode(mode & 0o777))?;
+    report(Progress::File)
+}
+
+// a member extract didnt write
+#[derive(Debug, PartialEq)]
+pub enum Skipped {
+    // its name or a symlink on the way points outside of dst
+    Outside(PathBuf),
+    // something is already there under its name
+    Exists(PathBuf),
+}
+
+// members run into whatever is already in dst, those are left alone
+fn skip_existing(
+    written: io::Result<()>,
+    name: &Path,
+    left_out: &mut Vec<Skipped>,
+) -> io::Result<()> {
+    match written {
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+            left_out.push(Skipped::Exists(name.to_path_buf()));
+            Ok(())
+        }
+        written => written,
+    }
+}
+
+// where a member ends up, None for members that arent under the path
+fn target(name: &Path, inner: &Path, dst: &Path) -> Option<PathBuf> {
+    let relative = clean(name)?.strip_prefix(inner).ok()?.to_path_buf();
+    match relative.as_os_str().is_empty() {
+        true => Some(dst.to_path_buf()),
+        false => Some(dst.join(relative)),
+    }
+}
+
+// target, but a name with ../ in it or a symlink somewhere on the way could
+// put the member anywhere on the disk, those go to left_out instead
+fn place(name: &Path, inner: &Path, dst: &Path, left_out: &mut Vec<Skipped>) -> Option<PathBuf> {
+    if name.components().any(|c| c == Component::ParentDir) {
+        left_out.push(Skipped::Outside(name.to_path_buf()));
+        return None;
+    }
+    let target = target(name, inner, dst)?;
+    let escapes = target
+        .strip_prefix(dst)
+        .ok()?
+        .ancestors()
+        .filter(|a| !a.as_os_str().is_empty())
+        .any(|a| dst.join(a).is_symlink());
+    if escapes {
+        left_out.push(Skipped::Outside(name.to_path_buf()));
+        return None;
+    }
+    Some(target)
+}
+
+// copies a member, everything under it or the whole archive to dst. members
+// that would end up outside of dst or on top of something are left out and
+// come back
+pub fn extract(path: &Path, dst: &Path, report: Report) -> io::Result<Vec<Skipped>> {
+    let (archive, inner) = locate(path).ok_or(Error::from(ErrorKind::NotFound))?;
+    let format = format(&archive).ok_or(Error::other("not an archive"))?;
+    let file = File::open(&archive)?;
+    if inner.as_os_str().is_empty() || is_dir(path) {
+        fs::create_dir_all(dst)?;
+    }
+    let mut left_out = vec![];
+    match format {
+        Format::Zip => {
+            let mut zip = zip::ZipArchive::new(file).map_err(Error::other)?;
+            for i in 0..zip.len() {
+                let mut entry = zip.by_index(i).map_err(Error::other)?;
+                let name = PathBuf::from(entry.name());
+                let target = match place(&name, &inner, dst, &mut left_out) {
+                    Some(target) => target,
+                    None => continue,
+                };
+                if entry.is_dir() {
+                    fs::create_dir_all(&target)?;
+                    continue;
+                }
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                if entry.is_symlink() {
+                    let mut link = String::new();
+                    entry.read_to_string(&mut link)?;
+                    skip_existing(symlink(link, &target), &name, &mut left_out)?;
+                    report(Progress::File)?;
+                    continue;
+                }
+                let mode = entry.unix_mode();
+                let written = write(&mut entry, &target, mode, report);
+                skip_existing(written, &name, &mut left_out)?;
+            }
+        }
+        _ => {
+            for entry in tar(&format, file).entries()? {
+                let mut entry = entry?;
+                let name = entry.path()?.to_path_buf();
+                let target = match place(&name, &inner, dst, &mut left_out) {
+                    Some(target) => target,
+                    None => continue,
+                };
+                let kind = entry.header().entry_type();
+                if kind.is_dir() {
+                    fs::create_dir_all(&target)?;
+                    continue;
+                }
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                if kind.is_symlink() {
+                    if let Some(link) = entry.link_name()? {
+                        skip_existing(symlink(link, &target), &name, &mut left_out)?;
+                        report(Progress::File)?;
+                    }
+                } else if kind.is_file() {
+                    let mode = entry.header().mode().ok();
+                    let written = write(&mut entry, &target, mode, report);
+                    skip_existing(written, &name, &mut left_out)?;
+                }
+            }
+        }
+    }
+    Ok(left_out)
+}
+
+static PARTS: AtomicUsize = AtomicUsize::new(0);
+
+fn scratch_path() -> PathBuf {
+    env::temp_dir().join(format!("lga-{}", process::id()))
+}
+
+// a real directory only we can get into, not something another user put
+// there under the name first
+fn ours(path: &Path) -> bool {
+    match path.symlink_metadata() {
+        Ok(metadata) => {
+            metadata.is_dir()
+                && metadata.uid() == unsafe { libc::geteuid() }
+                && metadata.mode() & 0o077 == 0
+        }
+        Err(_) => false,
+    }
+}
+
+// extracted members live here until lga exits. the name is easy to guess, so
+// whatever is already there has to be ours
+fn scratch() -> io::Result<PathBuf> {
+    let path = scratch_path();
+    match DirBuilder::new().mode(0o700).create(&path) {
+        Ok(_) => Ok(path),
+        Err(e) if e.kind() == ErrorKind::AlreadyExists && ours(&path) => Ok(path),
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => Err(Error::other(format!(
+            "{} belongs to someone else",
+            path.display()
+        ))),
+        Err(e) => Err(e),
+    }
+}
+
+// the member as a real file, extracted once per version of the archive
+pub fn materialize(path: &Path) -> io::Result<PathBuf> {
+    let (archive, inner) = split(path).ok_or(Error::from(ErrorKind::NotFound))?;
+    let mut hasher = DefaultHasher::new();
+    archive.hash(&mut hasher);
+    modified(&archive).hash(&mut hasher);
+    let target = scratch()?
+        .join(format!("{:x}", hasher.finish()))
+        .join(inner);
+    if target.symlink_metadata().is_err() {
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // under another name until its all there, the preview worker and the
+        // ui can both be at it
+        let part = target.with_file_name(format!(
+            ".{}.{}.part",
+            target.file_name().unwrap_or_default().to_string_lossy(),
+            PARTS.fetch_add(1, Ordering::Relaxed)
+        ));
+        extract(path, &part, &mut |_| Ok(()))?;
+        fs::rename(part, &target)?;
+    }
+    Ok(target)
+}
+
+pub fn cleanup() {
+    let path = scratch_path();
+    if ours(&path) {
+        let _ = fs::remove_dir_all(path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testdir::TestDir;
+    use zip::write::SimpleFileOptions;
+
+    type Make = fn(&Path, &[(&str, Option<&str>)]);
+
+    // a tar with names as they are, tar::Builder wont write the bad ones
+    fn raw_tar(path: &Path, entries: &[(&str, Option<&str>)]) {
+        let mut builder = tar::Builder::new(File::create(path).unwrap());
+        for (name, link) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.as_gnu_mut().unwrap().name[..name.len()].copy_from_slice(name.as_bytes());
+            header.set_mode(0o644);
+            match link {
+                Some(link) => {
+                    header.set_entry_type(tar::EntryType::Symlink);
+                    header.set_link_name(link).unwrap();
+                    header.set_size(0);
+                    header.set_cksum();
+                    builder.append(&header, io::empty()).unwrap();
+                }
+                None => {
+                    header.set_size(4);
+                    header.set_cksum();
+                    builder.append(&header, &b"evil"[..]).unwrap();
+                }
+            }
+        }
+        builder.finish().unwrap();
+    }
+
+    fn raw_zip(path: &Path, entries: &[(&str, Option<&str>)]) {
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        for (name, link) in entries {
+            match link {
+                Some(link) => zip
+                    .add_symlink(*name, *link, SimpleFileOptions::default())
+                    .unwrap(),
+                None => {
+                    zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+                    zip.write_all(b"evil").unwrap();
+                }
+            }
+        }
+        zip.finish().unwrap();
+    }
+
+    // extracts a whole archive with these entries, what was left out comes
+    // back
+    fn extract_all(name: &str, make: Make, entries: &[(&str, Option<&str>)]) -> Vec<Skipped> {
+        let root = TestDir::new(name);
+        make(&root.join("archive"), entries);
+        extract(&root.join("archive"), &root.join("dst"), &mut |_| Ok(())).unwrap()
+    }
+
+    #[test]
+    fn parent_dirs_are_left_out() {
+        for (name, make) in [("tar-up", raw_tar as Make), ("zip-up", raw_zip)] {
+            // ../x would land next to dst, in a directory nothing else uses
+            let root = TestDir::new(name);
+            let dst = root.join("dst");
+            make(&root.join("archive"), &[("../x", None), ("ok", None)]);
+            let left_out = extract(&root.join("archive"), &dst, &mut |_| Ok(())).unwrap();
+            assert_eq!(left_out, vec![Skipped::Outside(PathBuf::from("../x"))]);
+            assert_eq!(fs::read(dst.join("ok")).unwrap(), b"evil");
+            assert!(!root.join("x").exists());
+        }
+    }
+
+    #[test]
+    fn absolute_names_stay_inside() {
+        let dst = TestDir::new("absolute");
+        let target = place(Path::new("/etc/x"), Path::new(""), &dst, &mut vec![]);
+        assert_eq!(target, Some(dst.join("etc/x")));
+        for (name, make) in [("tar-abs", raw_tar as Make), ("zip-abs", raw_zip)] {
+            let root = TestDir::new(name);
+            let (archive, dst) = (root.join("archive"), root.join("dst"));
+            make(&archive, &[("/etc/lga-test", None)]);
+            assert!(extract(&archive, &dst, &mut |_| Ok(())).unwrap().is_empty());
+            assert_eq!(fs::read(dst.join("etc/lga-test")).unwrap(), b"evil");
+            assert!(!Path::new("/etc/lga-test").exists());
+        }
+    }
+
+    #[test]
+    fn nothing_goes_through_symlink_members() {
+        for (name, make) in [("tar-link", raw_tar as Make), ("zip-link", raw_zip)] {
+            let outside = TestDir::new(&format!("{name}-outside"));
+            let link = outside.to_string_lossy().to_string();
+            let left_out = extract_all(name, make, &[("link", Some(&link)), ("link/pwned", None)]);
+            assert_eq!(
+                left_out,
+                vec![Skipped::Outside(PathBuf::from("link/pwned"))]
+            );
+            assert!(!outside.join("pwned").exists());
+        }
+    }
+
+    #[test]
+    fn nothing_gets_overwritten_or_setuid() {
+        let root = TestDir::new("kept");
+        let dst = root.join("dst");
+        fs::create_dir_all(&dst).unwrap();
+        fs::write(dst.join("there"), "mine").unwrap();
+        symlink("there", dst.join("link")).unwrap();
+        // a setuid file, a file on top of one thats there and one on a link
+        let archive = root.join("archive");
+        let mut builder = tar::Builder::new(File::create(&archive).unwrap());
+        for (name, mode) in [("suid", 0o4755), ("there", 0o644), ("link", 0o644)] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(4);
+            header.set_mode(mode);
+            builder
+                .append_data(&mut header, name, &b"evil"[..])
+                .unwrap();
+        }
+        builder.finish().unwrap();
+        let left_out = extract(&archive, &dst, &mut |_| Ok(())).unwrap();
+        assert_eq!(
+            left_out,
+            vec![
+                Skipped::Exists(PathBuf::from("there")),
+                // writing to the link would write where it points
+                Skipped::Outside(PathBuf::from("link"))
+            ]
+        );
+        assert_eq!(fs::read(dst.join("there")).unwrap(), b"mine");
+        assert_eq!(dst.join("suid").metadata().unwrap().mode() & 0o7777, 0o755);
+    }
+
+    #[test]
+    fn scratch_has_to_be_ours() {
+        let path = scratch_path();
+        let _ = fs::remove_dir_all(&path);
+        let _ = fs::remove_file(&path);
+        // someone got there first with a link to somewhere they can read
+        symlink(env::temp_dir(), &path).unwrap();
+        assert!(scratch().is_err());
+        cleanup();
+        assert!(path.symlink_metadata().is_ok());
+        fs::remove_file(&path).unwrap();
+        fs::create_dir(&path).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o777)).unwrap();
+        assert!(scratch().is_err());
+        fs::remove_dir(&path).unwrap();
+        assert_eq!(scratch().unwrap(), path);
+        assert_eq!(path.metadata().unwrap().mode() & 0o777, 0o700);
+        // and again once it is there
+        assert!(scratch().is_ok());
+        cleanup();
+        assert!(path.symlink_metadata().is_err());
+    }
+}